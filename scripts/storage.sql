@@ -3,4 +3,198 @@ CREATE TABLE IF NOT EXISTS bckt_links (
   owner_email VARCHAR(64) NOT NULL,
   link_hash VARCHAR(8) NOT NULL,
   link_long TEXT NOT NULL
-);
\ No newline at end of file
+);
+
+CREATE TABLE IF NOT EXISTS bckt_workspaces (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(64) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS bckt_workspace_members (
+  workspace_id INTEGER NOT NULL REFERENCES bckt_workspaces (id) ON DELETE CASCADE,
+  member_email VARCHAR(64) NOT NULL,
+  member_display VARCHAR(320) NOT NULL,
+  role VARCHAR(8) NOT NULL,
+  PRIMARY KEY (workspace_id, member_email)
+);
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS workspace_id INTEGER REFERENCES bckt_workspaces (id) ON DELETE SET NULL;
+
+CREATE TABLE IF NOT EXISTS bckt_link_transfers (
+  id SERIAL PRIMARY KEY,
+  link_id INTEGER NOT NULL UNIQUE REFERENCES bckt_links (id) ON DELETE CASCADE,
+  sender_email VARCHAR(64) NOT NULL,
+  sender_display VARCHAR(320) NOT NULL,
+  recipient_email VARCHAR(64) NOT NULL,
+  recipient_display VARCHAR(320) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS bckt_domains (
+  host VARCHAR(253) PRIMARY KEY,
+  owner_email VARCHAR(64),
+  workspace_id INTEGER REFERENCES bckt_workspaces (id) ON DELETE CASCADE,
+  CHECK ((owner_email IS NULL) != (workspace_id IS NULL))
+);
+
+ALTER TABLE bckt_domains
+  ADD COLUMN IF NOT EXISTS verification_token VARCHAR(32),
+  ADD COLUMN IF NOT EXISTS verified_at TIMESTAMPTZ;
+
+-- domains claimed before they had to be verified remain active
+UPDATE bckt_domains SET verified_at = NOW()
+  WHERE verification_token IS NULL AND verified_at IS NULL;
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS domain VARCHAR(253) REFERENCES bckt_domains (host) ON DELETE CASCADE;
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS link_canonical TEXT;
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS clicks BIGINT NOT NULL DEFAULT 0;
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS clicks_bot BIGINT NOT NULL DEFAULT 0;
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS clicks_preview BIGINT NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS bckt_collections (
+  id SERIAL PRIMARY KEY,
+  slug VARCHAR(32) NOT NULL UNIQUE,
+  owner_email VARCHAR(64) NOT NULL,
+  title VARCHAR(128) NOT NULL,
+  description TEXT NOT NULL DEFAULT ''
+);
+
+CREATE TABLE IF NOT EXISTS bckt_collection_items (
+  id SERIAL PRIMARY KEY,
+  collection_id INTEGER NOT NULL REFERENCES bckt_collections (id) ON DELETE CASCADE,
+  link_id INTEGER NOT NULL REFERENCES bckt_links (id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  title VARCHAR(128) NOT NULL,
+  description TEXT NOT NULL DEFAULT ''
+);
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS preview_title VARCHAR(256),
+  ADD COLUMN IF NOT EXISTS preview_description TEXT,
+  ADD COLUMN IF NOT EXISTS preview_image TEXT;
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS page_title VARCHAR(256),
+  ADD COLUMN IF NOT EXISTS page_favicon TEXT,
+  ADD COLUMN IF NOT EXISTS page_meta_override BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS title VARCHAR(256);
+
+ALTER TABLE bckt_links
+  ADD COLUMN IF NOT EXISTS note TEXT;
+
+CREATE TABLE IF NOT EXISTS bckt_webhooks (
+  id SERIAL PRIMARY KEY,
+  owner_email VARCHAR(64) NOT NULL,
+  link_id INTEGER REFERENCES bckt_links (id) ON DELETE CASCADE,
+  url TEXT NOT NULL,
+  secret VARCHAR(64) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS bckt_webhook_deliveries (
+  id SERIAL PRIMARY KEY,
+  webhook_id INTEGER NOT NULL REFERENCES bckt_webhooks (id) ON DELETE CASCADE,
+  event_id VARCHAR(32) NOT NULL,
+  attempt INTEGER NOT NULL,
+  status INTEGER,
+  error TEXT,
+  delivered_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS bckt_webhook_deliveries_webhook_id ON bckt_webhook_deliveries (webhook_id, delivered_at DESC);
+
+CREATE TABLE IF NOT EXISTS bckt_link_clicks_daily (
+  link_id INTEGER NOT NULL REFERENCES bckt_links (id) ON DELETE CASCADE,
+  day DATE NOT NULL,
+  clicks BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY (link_id, day)
+);
+
+CREATE TABLE IF NOT EXISTS bckt_link_referrers (
+  link_id INTEGER NOT NULL REFERENCES bckt_links (id) ON DELETE CASCADE,
+  referrer_host VARCHAR(253) NOT NULL,
+  clicks BIGINT NOT NULL DEFAULT 0,
+  first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (link_id, referrer_host)
+);
+
+CREATE TABLE IF NOT EXISTS bckt_preferences (
+  owner_email VARCHAR(64) PRIMARY KEY,
+  digest_email VARCHAR(320),
+  digest_sent_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS bckt_secrets (
+  id VARCHAR(32) PRIMARY KEY,
+  owner_email VARCHAR(64) NOT NULL,
+  ciphertext TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- the ciphertext of a secret is removed once it is burned, its metadata is kept
+ALTER TABLE bckt_secrets ALTER COLUMN ciphertext DROP NOT NULL;
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS burn_after_reading BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS viewed_at TIMESTAMPTZ;
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ NOT NULL DEFAULT NOW() + INTERVAL '1 day';
+
+CREATE INDEX IF NOT EXISTS bckt_secrets_expires_at ON bckt_secrets (expires_at);
+
+-- secrets protected by a passphrase, of which only a hash of the client-derived verifier is known
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS passphrase_salt VARCHAR(32);
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS passphrase_verifier VARCHAR(64);
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS attempts_left INTEGER;
+
+-- encrypted files, stored in a blob store, and deleted once no secret needs them anymore
+CREATE TABLE IF NOT EXISTS bckt_blobs (
+  key VARCHAR(64) PRIMARY KEY,
+  owner_email VARCHAR(64) NOT NULL,
+  size BIGINT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS file_key VARCHAR(64);
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS file_size BIGINT;
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS file_ticket VARCHAR(32);
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS file_ticket_expires_at TIMESTAMPTZ;
+
+-- read receipts, for which the creator opts in with the address to notify
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS receipt_email VARCHAR(320);
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS viewed_by VARCHAR(128);
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS revoked_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS bckt_secrets_owner_email ON bckt_secrets (owner_email, created_at DESC);
+
+-- requests for a secret, answered by anyone with the link, encrypted to the public key of its creator
+CREATE TABLE IF NOT EXISTS bckt_secret_requests (
+  id VARCHAR(32) PRIMARY KEY,
+  owner_email VARCHAR(64) NOT NULL,
+  label VARCHAR(200) NOT NULL,
+  public_key VARCHAR(128) NOT NULL,
+  ciphertext TEXT,
+  ephemeral_key VARCHAR(128),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  answered_at TIMESTAMPTZ,
+  expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS bckt_secret_requests_owner_email ON bckt_secret_requests (owner_email, created_at DESC);
+CREATE INDEX IF NOT EXISTS bckt_secret_requests_expires_at ON bckt_secret_requests (expires_at);
+
+-- secrets which burn after a number of views, rather than after the first one
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS max_views INTEGER;
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS views_left INTEGER;
+
+-- secrets which can only be read by a recipient logged in with this email address
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS recipient_email_hash VARCHAR(64);
+-- when a login link was last sent to the recipient, which is only sent again after a cooldown
+ALTER TABLE bckt_secrets ADD COLUMN IF NOT EXISTS recipient_login_sent_at TIMESTAMPTZ;
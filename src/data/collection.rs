@@ -0,0 +1,88 @@
+/// A public, curated and ordered collection of shortlinks,
+/// rendered at `/@{slug}`.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Collection {
+    id: i32,
+    slug: String,
+    title: String,
+    description: String,
+}
+
+impl Collection {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn slug(&self) -> &str {
+        &self.slug
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Slugs are between 3 and 32 characters long,
+    /// using only lowercase ascii letters, digits, dashes and underscores.
+    pub fn is_valid_slug(slug: &str) -> bool {
+        (3..=32).contains(&slug.len())
+            && slug
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CollectionItem {
+    id: i32,
+    link_hash: String,
+    domain: Option<String>,
+    clicks: i64,
+    title: String,
+    description: String,
+}
+
+impl CollectionItem {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn clicks(&self) -> i64 {
+        self.clicks
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Render the shortlink of this item, such that clicks are counted for it,
+    /// using the given scheme and host of the default namespace like [`super::Shortlink::link_short`].
+    pub fn link_short(&self, scheme: &str, host: &str) -> String {
+        match &self.domain {
+            Some(domain) => format!("https://{}/{}", domain, self.link_hash),
+            None => format!("{}://{}/{}", scheme, host, self.link_hash),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_valid_slug() {
+        assert!(Collection::is_valid_slug("plabayo"));
+        assert!(Collection::is_valid_slug("my-links_2023"));
+        assert!(!Collection::is_valid_slug("ab"));
+        assert!(!Collection::is_valid_slug("Plabayo"));
+        assert!(!Collection::is_valid_slug("@plabayo"));
+        assert!(!Collection::is_valid_slug(&"a".repeat(33)));
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+
+/// Prefix of the name of the TXT record proving the ownership of a domain.
+pub const VERIFICATION_RECORD_PREFIX: &str = "_bckt-challenge";
+
+/// A custom domain, serving its own namespace of shortlinks,
+/// owned either by a single user or by a workspace.
+///
+/// A claimed domain only serves shortlinks once it is verified, by adding
+/// a TXT record containing the token of the claim to its DNS records.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Domain {
+    host: String,
+    owner_email: Option<String>,
+    workspace_id: Option<i32>,
+    verification_token: Option<String>,
+    verified_at: Option<DateTime<Utc>>,
+}
+
+impl Domain {
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn owner_email(&self) -> Option<&str> {
+        self.owner_email.as_deref()
+    }
+
+    pub fn workspace_id(&self) -> Option<i32> {
+        self.workspace_id
+    }
+
+    pub fn verification_token(&self) -> Option<&str> {
+        self.verification_token.as_deref()
+    }
+
+    pub fn is_verified(&self) -> bool {
+        self.verified_at.is_some()
+    }
+
+    /// The name of the TXT record to verify the domain with.
+    pub fn verification_name(&self) -> String {
+        format!("{VERIFICATION_RECORD_PREFIX}.{}", self.host)
+    }
+
+    /// The value of the TXT record to verify the domain with.
+    pub fn verification_value(&self) -> String {
+        format!(
+            "bckt-verification={}",
+            self.verification_token.as_deref().unwrap_or_default()
+        )
+    }
+
+    /// Whether or not one of the TXT records proves the claim on the domain.
+    pub fn is_verified_by(&self, records: &[String]) -> bool {
+        self.verification_token.is_some()
+            && records
+                .iter()
+                .any(|record| record.trim() == self.verification_value())
+    }
+
+    /// Whether or not the host is served by bckt.xyz itself, or by one of its subdomains.
+    pub fn is_reserved_host(host: &str) -> bool {
+        host == "bckt.xyz" || host.ends_with(".bckt.xyz")
+    }
+
+    /// Normalize the value of a `Host` header to the host name used for domains,
+    /// meaning it is lowercased, stripped of its port and encoded as punycode.
+    pub fn normalize_host(host: &str) -> String {
+        let host = host.trim().to_lowercase();
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name.to_owned(),
+            _ => host,
+        };
+        crate::services::idn::to_ascii(&host).unwrap_or(host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domain(verification_token: Option<&str>) -> Domain {
+        Domain {
+            host: "go.example.com".to_owned(),
+            owner_email: Some("owner".to_owned()),
+            workspace_id: None,
+            verification_token: verification_token.map(str::to_owned),
+            verified_at: None,
+        }
+    }
+
+    #[test]
+    fn test_domain_is_verified_by() {
+        let domain = domain(Some("token"));
+        assert_eq!(domain.verification_name(), "_bckt-challenge.go.example.com");
+        assert!(domain.is_verified_by(&["other".to_owned(), "bckt-verification=token".to_owned()]));
+        assert!(!domain.is_verified_by(&["bckt-verification=other".to_owned()]));
+        assert!(!domain.is_verified_by(&[]));
+        // a claim without a token can never be verified
+        assert!(!self::domain(None).is_verified_by(&["bckt-verification=".to_owned()]));
+    }
+
+    #[test]
+    fn test_domain_is_reserved_host() {
+        assert!(Domain::is_reserved_host("bckt.xyz"));
+        assert!(Domain::is_reserved_host("www.bckt.xyz"));
+        assert!(!Domain::is_reserved_host("mybckt.xyz"));
+        assert!(!Domain::is_reserved_host("bckt.xyz.example.com"));
+    }
+
+    #[test]
+    fn test_domain_normalize_host() {
+        assert_eq!(Domain::normalize_host("go.example.com"), "go.example.com");
+        assert_eq!(Domain::normalize_host("Go.Example.COM"), "go.example.com");
+        assert_eq!(Domain::normalize_host("localhost:8000"), "localhost");
+        assert_eq!(Domain::normalize_host(" bckt.xyz:443 "), "bckt.xyz");
+        assert_eq!(
+            Domain::normalize_host("Bücher.example:8080"),
+            "xn--bcher-kva.example"
+        );
+    }
+}
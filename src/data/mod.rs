@@ -1,2 +1,23 @@
+mod collection;
+pub use collection::{Collection, CollectionItem};
+
+mod domain;
+pub use domain::Domain;
+
+mod secret;
+pub use secret::{Secret, SecretReveal, MAX_FILE_CIPHERTEXT_LEN, MAX_FILE_SIZE, MAX_TTL_HOURS};
+
+mod secret_request;
+pub use secret_request::SecretRequest;
+
 mod shortlink;
 pub use shortlink::Shortlink;
+
+mod transfer;
+pub use transfer::LinkTransfer;
+
+mod webhook;
+pub use webhook::{Webhook, WebhookDelivery};
+
+mod workspace;
+pub use workspace::{Workspace, WorkspaceMember, WorkspaceRole};
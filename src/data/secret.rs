@@ -0,0 +1,430 @@
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+use orion::hazardous::hash::sha2::sha256::Sha256;
+
+/// The maximum length of the (base64url encoded) ciphertext of a secret.
+pub const MAX_CIPHERTEXT_LEN: usize = 64 * 1024;
+
+/// The shortest time a secret can live, in hours.
+pub const MIN_TTL_HOURS: i64 = 1;
+/// The longest time a secret can live, in hours, after which it is always purged.
+pub const MAX_TTL_HOURS: i64 = 30 * 24;
+
+/// The maximum size of a file shared as a secret.
+pub const MAX_FILE_SIZE: u64 = 25 * 1024 * 1024;
+/// The maximum size of an encrypted file, which is prefixed by its nonce and suffixed by its tag.
+pub const MAX_FILE_CIPHERTEXT_LEN: u64 = MAX_FILE_SIZE + 12 + 16;
+
+/// The maximum amount of views of a secret which burns after reading.
+pub const MAX_VIEWS: i32 = 100;
+
+/// The amount of wrong passphrases after which a secret is destroyed.
+pub const MAX_PASSPHRASE_ATTEMPTS: i32 = 5;
+
+/// The outcome of an attempt to reveal a secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretReveal {
+    /// The ciphertext of the secret, and a ticket to download its file, if it has one.
+    Revealed {
+        ciphertext: String,
+        file_ticket: Option<String>,
+        /// The address to send a read receipt to, if the creator asked for one.
+        receipt_email: Option<String>,
+    },
+    WrongPassphrase {
+        attempts_left: i32,
+    },
+    /// The secret is bound to a recipient, as whom the visitor is not logged in.
+    NotRecipient,
+    /// The secret was already viewed, expired or was destroyed.
+    Gone,
+}
+
+/// What happened to a secret, as shown to its creator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretStatus {
+    Unread,
+    Read,
+    Expired,
+    /// Revoked by its creator, or destroyed after too many wrong passphrases.
+    Revoked,
+}
+
+impl fmt::Display for SecretStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SecretStatus::Unread => "unread",
+            SecretStatus::Read => "read",
+            SecretStatus::Expired => "expired",
+            SecretStatus::Revoked => "revoked",
+        })
+    }
+}
+
+/// A secret, of which only the ciphertext is stored.
+///
+/// Secrets are encrypted in the browser using AES-GCM, with a key that is only
+/// ever part of the fragment of the secret url, and thus never sent to us.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Secret {
+    id: String,
+    owner_email: String,
+    ciphertext: Option<String>,
+    burn_after_reading: bool,
+    created_at: DateTime<Utc>,
+    viewed_at: Option<DateTime<Utc>>,
+    expires_at: DateTime<Utc>,
+    passphrase_salt: Option<String>,
+    passphrase_verifier: Option<String>,
+    attempts_left: Option<i32>,
+    file_key: Option<String>,
+    file_size: Option<i64>,
+    receipt_email: Option<String>,
+    viewed_by: Option<String>,
+    revoked_at: Option<DateTime<Utc>>,
+    max_views: Option<i32>,
+    views_left: Option<i32>,
+    recipient_email_hash: Option<String>,
+}
+
+impl Secret {
+    pub fn new(
+        ciphertext: String,
+        owner_email: String,
+        burn_after_reading: bool,
+        ttl_hours: i64,
+    ) -> Self {
+        let created_at = Utc::now();
+        Self {
+            id: nanoid::nanoid!(22),
+            owner_email,
+            ciphertext: Some(ciphertext),
+            burn_after_reading,
+            created_at,
+            viewed_at: None,
+            expires_at: created_at + Duration::hours(ttl_hours),
+            passphrase_salt: None,
+            passphrase_verifier: None,
+            attempts_left: None,
+            file_key: None,
+            file_size: None,
+            receipt_email: None,
+            viewed_by: None,
+            revoked_at: None,
+            max_views: burn_after_reading.then_some(1),
+            views_left: burn_after_reading.then_some(1),
+            recipient_email_hash: None,
+        }
+    }
+
+    /// Bind the secret to a recipient, identified by the hash of their email address,
+    /// who has to log in as such to read it.
+    pub fn with_recipient(mut self, email_hash: String) -> Self {
+        self.recipient_email_hash = Some(email_hash);
+        self
+    }
+
+    /// Burn the secret once it has been viewed the given amount of times.
+    pub fn with_max_views(mut self, max_views: i32) -> Self {
+        self.burn_after_reading = true;
+        self.max_views = Some(max_views);
+        self.views_left = Some(max_views);
+        self
+    }
+
+    pub fn is_valid_max_views(max_views: i32) -> bool {
+        (1..=MAX_VIEWS).contains(&max_views)
+    }
+
+    /// Notify the creator at the given email address once the secret is read.
+    pub fn with_read_receipt(mut self, email: String) -> Self {
+        self.receipt_email = Some(email);
+        self
+    }
+
+    /// Attach an encrypted file, stored as a blob under the given key.
+    pub fn with_file(mut self, key: String, size: i64) -> Self {
+        self.file_key = Some(key);
+        self.file_size = Some(size);
+        self
+    }
+
+    /// Protect the secret with a passphrase, which the browser stretched using the salt,
+    /// and combined with the key of the secret into the verifier.
+    pub fn with_passphrase(mut self, salt: String, verifier: &str) -> Self {
+        self.passphrase_salt = Some(salt);
+        self.passphrase_verifier = Some(Self::hash_verifier(verifier));
+        self.attempts_left = Some(MAX_PASSPHRASE_ATTEMPTS);
+        self
+    }
+
+    /// Whether or not the value is the base64url (unpadded) encoding of `len` bytes.
+    pub fn is_valid_encoding(value: &str, len: usize) -> bool {
+        value.len() == (len * 4).div_ceil(3)
+            && value
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    }
+
+    /// Hash the verifier of a passphrase, such that a leaked database cannot be used
+    /// to reveal secrets without knowing their passphrase.
+    pub fn hash_verifier(verifier: &str) -> String {
+        let digest = Sha256::digest(verifier.as_bytes()).expect("hash passphrase verifier");
+        hex::encode(digest.as_ref())
+    }
+
+    /// Compare a verifier against a stored verifier hash, in constant time.
+    pub fn verify_passphrase(verifier_hash: &str, verifier: &str) -> bool {
+        orion::util::secure_cmp(
+            Self::hash_verifier(verifier).as_bytes(),
+            verifier_hash.as_bytes(),
+        )
+        .is_ok()
+    }
+
+    pub fn is_valid_ttl(ttl_hours: i64) -> bool {
+        (MIN_TTL_HOURS..=MAX_TTL_HOURS).contains(&ttl_hours)
+    }
+
+    /// Whether or not the ciphertext looks like what the browser produces:
+    /// the base64url encoded (unpadded) nonce followed by the encrypted secret.
+    pub fn is_valid_ciphertext(ciphertext: &str) -> bool {
+        // a 12 byte nonce and 16 byte tag, encoded, is at least 38 characters
+        ciphertext.len() >= 38
+            && ciphertext.len() <= MAX_CIPHERTEXT_LEN
+            && ciphertext
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn owner_email(&self) -> &str {
+        &self.owner_email
+    }
+
+    /// The base64url encoded nonce and encrypted secret,
+    /// which is gone once a burn-after-reading secret has been viewed.
+    pub fn ciphertext(&self) -> Option<&str> {
+        self.ciphertext.as_deref()
+    }
+
+    pub fn burn_after_reading(&self) -> bool {
+        self.burn_after_reading
+    }
+
+    pub fn is_burned(&self) -> bool {
+        self.ciphertext.is_none()
+    }
+
+    /// The amount of times a secret which burns after reading can be viewed in total.
+    ///
+    /// Secrets which burned after reading before views were counted could be viewed once.
+    pub fn max_views(&self) -> Option<i32> {
+        self.max_views
+            .or_else(|| self.burn_after_reading.then_some(1))
+    }
+
+    /// The amount of times the secret can still be viewed, if it burns after reading.
+    pub fn views_left(&self) -> Option<i32> {
+        match self.views_left {
+            Some(views_left) => Some(views_left),
+            None if self.burn_after_reading => Some(if self.is_burned() { 0 } else { 1 }),
+            None => None,
+        }
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// Whether or not the secret can still be read, and thus revoked or extended.
+    pub fn is_active(&self) -> bool {
+        !self.is_burned() && !self.is_expired()
+    }
+
+    /// Whether or not the expiry of the secret can be extended,
+    /// which is never beyond the longest time a secret can live.
+    pub fn can_extend(&self) -> bool {
+        self.is_active() && self.expires_at < self.created_at + Duration::hours(MAX_TTL_HOURS)
+    }
+
+    /// The salt used to stretch the passphrase, if the secret is protected by one.
+    pub fn passphrase_salt(&self) -> Option<&str> {
+        self.passphrase_salt.as_deref()
+    }
+
+    pub fn passphrase_verifier(&self) -> Option<&str> {
+        self.passphrase_verifier.as_deref()
+    }
+
+    pub fn attempts_left(&self) -> Option<i32> {
+        self.attempts_left
+    }
+
+    /// The key of the blob containing the encrypted file of this secret, if any.
+    pub fn file_key(&self) -> Option<&str> {
+        self.file_key.as_deref()
+    }
+
+    /// The size of the encrypted file of this secret, if any.
+    pub fn file_size(&self) -> Option<i64> {
+        self.file_size
+    }
+
+    /// The hash of the email address of the only recipient allowed to read the secret, if any.
+    pub fn recipient_email_hash(&self) -> Option<&str> {
+        self.recipient_email_hash.as_deref()
+    }
+
+    /// Whether or not the visitor, identified by the hash of their email address if logged in,
+    /// is allowed to read the secret.
+    pub fn is_readable_by(&self, email_hash: Option<&str>) -> bool {
+        match self.recipient_email_hash.as_deref() {
+            Some(recipient) => email_hash == Some(recipient),
+            None => true,
+        }
+    }
+
+    /// The address notified when the secret is read, if its creator asked for a read receipt.
+    pub fn receipt_email(&self) -> Option<&str> {
+        self.receipt_email.as_deref()
+    }
+
+    /// A coarse description of the client which last revealed the secret,
+    /// only known for secrets with a read receipt.
+    pub fn viewed_by(&self) -> Option<&str> {
+        self.viewed_by.as_deref()
+    }
+
+    pub fn status(&self) -> SecretStatus {
+        if self.revoked_at.is_some() {
+            SecretStatus::Revoked
+        } else if self.is_expired() {
+            SecretStatus::Expired
+        } else if self.viewed_at.is_some() {
+            SecretStatus::Read
+        } else if self.ciphertext.is_none() {
+            SecretStatus::Revoked
+        } else {
+            SecretStatus::Unread
+        }
+    }
+
+    /// When the secret was last revealed, if ever.
+    pub fn viewed_at(&self) -> Option<DateTime<Utc>> {
+        self.viewed_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_ciphertext() {
+        assert!(Secret::is_valid_ciphertext(&"aB3-_".repeat(10)));
+        assert!(!Secret::is_valid_ciphertext("tooshort"));
+        assert!(!Secret::is_valid_ciphertext(&"a+/=".repeat(10)));
+        assert!(!Secret::is_valid_ciphertext(
+            &"a".repeat(MAX_CIPHERTEXT_LEN + 1)
+        ));
+    }
+
+    #[test]
+    fn test_passphrase() {
+        assert!(Secret::is_valid_encoding(&"A".repeat(22), 16));
+        assert!(Secret::is_valid_encoding(
+            &format!("{}a", "-_".repeat(21)),
+            32
+        ));
+        assert!(!Secret::is_valid_encoding(&"A".repeat(24), 16));
+        assert!(!Secret::is_valid_encoding(&"+".repeat(22), 16));
+
+        let secret = Secret::new("a".repeat(40), "owner".to_owned(), false, 1)
+            .with_passphrase("A".repeat(22), "verifier");
+        assert_eq!(secret.attempts_left(), Some(MAX_PASSPHRASE_ATTEMPTS));
+        let hash = secret.passphrase_verifier().unwrap();
+        assert_ne!(hash, "verifier");
+        assert!(Secret::verify_passphrase(hash, "verifier"));
+        assert!(!Secret::verify_passphrase(hash, "wrong"));
+    }
+
+    #[test]
+    fn test_status() {
+        let mut secret = Secret::new("a".repeat(40), "owner".to_owned(), false, 1);
+        assert_eq!(secret.status(), SecretStatus::Unread);
+        secret.viewed_at = Some(Utc::now());
+        assert_eq!(secret.status(), SecretStatus::Read);
+        assert!(secret.is_active());
+        assert!(secret.can_extend());
+        secret.expires_at = secret.created_at + Duration::hours(MAX_TTL_HOURS);
+        assert!(!secret.can_extend());
+        secret.expires_at = Utc::now();
+        assert_eq!(secret.status(), SecretStatus::Expired);
+        assert!(!secret.is_active());
+        secret.revoked_at = Some(Utc::now());
+        assert_eq!(secret.status(), SecretStatus::Revoked);
+        assert_eq!(secret.status().to_string(), "revoked");
+    }
+
+    #[test]
+    fn test_max_views() {
+        assert!(Secret::is_valid_max_views(1));
+        assert!(Secret::is_valid_max_views(MAX_VIEWS));
+        assert!(!Secret::is_valid_max_views(0));
+        assert!(!Secret::is_valid_max_views(MAX_VIEWS + 1));
+
+        let secret = Secret::new("a".repeat(40), "owner".to_owned(), false, 1);
+        assert_eq!(secret.views_left(), None);
+        let secret = secret.with_max_views(5);
+        assert!(secret.burn_after_reading());
+        assert_eq!(secret.max_views(), Some(5));
+        assert_eq!(secret.views_left(), Some(5));
+
+        // secrets which burned after reading before views were counted
+        let mut secret = Secret::new("a".repeat(40), "owner".to_owned(), true, 1);
+        secret.max_views = None;
+        secret.views_left = None;
+        assert_eq!(secret.max_views(), Some(1));
+        assert_eq!(secret.views_left(), Some(1));
+        secret.ciphertext = None;
+        assert_eq!(secret.views_left(), Some(0));
+    }
+
+    #[test]
+    fn test_is_readable_by() {
+        let secret = Secret::new("a".repeat(40), "owner".to_owned(), false, 1);
+        assert!(secret.is_readable_by(None));
+        assert!(secret.is_readable_by(Some("visitor")));
+
+        let secret = secret.with_recipient("recipient".to_owned());
+        assert!(!secret.is_readable_by(None));
+        assert!(!secret.is_readable_by(Some("visitor")));
+        assert!(secret.is_readable_by(Some("recipient")));
+    }
+
+    #[test]
+    fn test_ttl() {
+        assert!(Secret::is_valid_ttl(1));
+        assert!(Secret::is_valid_ttl(30 * 24));
+        assert!(!Secret::is_valid_ttl(0));
+        assert!(!Secret::is_valid_ttl(30 * 24 + 1));
+
+        let secret = Secret::new("a".repeat(40), "owner".to_owned(), false, 1);
+        assert!(!secret.is_expired());
+        let secret = Secret::new("a".repeat(40), "owner".to_owned(), false, -1);
+        assert!(secret.is_expired());
+    }
+}
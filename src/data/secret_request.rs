@@ -0,0 +1,141 @@
+use chrono::{DateTime, Duration, Utc};
+
+use super::Secret;
+
+/// The maximum length of the label describing what is requested.
+pub const MAX_LABEL_LEN: usize = 200;
+
+/// A request for someone, who does not need an account, to send a secret to its creator.
+///
+/// The browser of the creator generates an ECDH (P-256) key pair, of which the private key
+/// never leaves it. The browser of the sender derives an AES-GCM key from the public key
+/// and an ephemeral key pair, such that only the creator can decrypt the answer.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SecretRequest {
+    id: String,
+    owner_email: String,
+    label: String,
+    public_key: String,
+    ciphertext: Option<String>,
+    ephemeral_key: Option<String>,
+    created_at: DateTime<Utc>,
+    answered_at: Option<DateTime<Utc>>,
+    expires_at: DateTime<Utc>,
+}
+
+impl SecretRequest {
+    pub fn new(owner_email: String, label: String, public_key: String, ttl_hours: i64) -> Self {
+        let created_at = Utc::now();
+        Self {
+            id: nanoid::nanoid!(22),
+            owner_email,
+            label,
+            public_key,
+            ciphertext: None,
+            ephemeral_key: None,
+            created_at,
+            answered_at: None,
+            expires_at: created_at + Duration::hours(ttl_hours),
+        }
+    }
+
+    /// Whether or not the key is a base64url encoded, uncompressed P-256 public key,
+    /// which always starts with a `0x04` byte.
+    pub fn is_valid_public_key(public_key: &str) -> bool {
+        Secret::is_valid_encoding(public_key, 65) && public_key.starts_with('B')
+    }
+
+    pub fn is_valid_label(label: &str) -> bool {
+        !label.trim().is_empty() && label.len() <= MAX_LABEL_LEN
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn owner_email(&self) -> &str {
+        &self.owner_email
+    }
+
+    /// What the creator asks to be sent, as shown to the sender.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The base64url encoded public key of the creator, to encrypt the answer to.
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// The answer, encrypted like a secret, which is gone once the request expired.
+    pub fn ciphertext(&self) -> Option<&str> {
+        self.ciphertext.as_deref()
+    }
+
+    /// The public key of the ephemeral key pair the sender encrypted the answer with.
+    pub fn ephemeral_key(&self) -> Option<&str> {
+        self.ephemeral_key.as_deref()
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn answered_at(&self) -> Option<DateTime<Utc>> {
+        self.answered_at
+    }
+
+    pub fn is_answered(&self) -> bool {
+        self.answered_at.is_some()
+    }
+
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_public_key() {
+        assert!(SecretRequest::is_valid_public_key(&format!(
+            "B{}",
+            "a".repeat(86)
+        )));
+        assert!(!SecretRequest::is_valid_public_key(&format!(
+            "A{}",
+            "a".repeat(86)
+        )));
+        assert!(!SecretRequest::is_valid_public_key(&"B".repeat(88)));
+        assert!(!SecretRequest::is_valid_public_key(&format!(
+            "B{}",
+            "+".repeat(86)
+        )));
+    }
+
+    #[test]
+    fn test_is_valid_label() {
+        assert!(SecretRequest::is_valid_label("the database password"));
+        assert!(!SecretRequest::is_valid_label("  "));
+        assert!(!SecretRequest::is_valid_label(
+            &"a".repeat(MAX_LABEL_LEN + 1)
+        ));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let request =
+            SecretRequest::new("owner".to_owned(), "label".to_owned(), "key".to_owned(), 1);
+        assert!(!request.is_expired());
+        assert!(!request.is_answered());
+        let request =
+            SecretRequest::new("owner".to_owned(), "label".to_owned(), "key".to_owned(), -1);
+        assert!(request.is_expired());
+    }
+}
@@ -1,17 +1,78 @@
+use reqwest::Url;
+
+/// Query parameters which only serve to track visitors across sites,
+/// without changing the destination of a link.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "ttclid",
+    "igshid",
+    "li_fat_id",
+    "mc_cid",
+    "mc_eid",
+    "mkt_tok",
+    "_hsenc",
+    "_hsmi",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+];
+
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct Shortlink {
     owner_email: String,
     link_hash: String,
     link_long: String,
+    link_canonical: Option<String>,
+    workspace_id: Option<i32>,
+    domain: Option<String>,
+    clicks: i64,
+    clicks_bot: i64,
+    clicks_preview: i64,
+    preview_title: Option<String>,
+    preview_description: Option<String>,
+    preview_image: Option<String>,
+    page_title: Option<String>,
+    page_favicon: Option<String>,
+    page_meta_override: bool,
+    title: Option<String>,
+    note: Option<String>,
 }
 
 impl Shortlink {
-    pub fn new(link_long: String, owner_email: String) -> Self {
+    pub fn new(
+        link_long: String,
+        link_canonical: String,
+        owner_email: String,
+        workspace_id: Option<i32>,
+        domain: Option<String>,
+    ) -> Self {
         let link_hash = nanoid::nanoid!(8);
         Self {
             owner_email,
             link_hash,
             link_long,
+            link_canonical: Some(link_canonical),
+            workspace_id,
+            domain,
+            clicks: 0,
+            clicks_bot: 0,
+            clicks_preview: 0,
+            preview_title: None,
+            preview_description: None,
+            preview_image: None,
+            page_title: None,
+            page_favicon: None,
+            page_meta_override: false,
+            title: None,
+            note: None,
         }
     }
 
@@ -19,15 +80,231 @@ impl Shortlink {
         &self.owner_email
     }
 
+    /// The long link as it was submitted by its creator.
     pub fn link_long(&self) -> &str {
         &self.link_long
     }
 
+    /// The canonical form of the long link, which is where the shortlink redirects to.
+    pub fn link_canonical(&self) -> &str {
+        // shortlinks created before canonicalisation only have their original form
+        self.link_canonical.as_deref().unwrap_or(&self.link_long)
+    }
+
     pub fn link_hash(&self) -> &str {
         &self.link_hash
     }
 
+    pub fn workspace_id(&self) -> Option<i32> {
+        self.workspace_id
+    }
+
+    /// The custom domain this shortlink lives on, if not the default one.
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// The amount of times a person followed this shortlink.
+    pub fn clicks(&self) -> i64 {
+        self.clicks
+    }
+
+    /// The amount of times a crawler, monitor or scanner requested this shortlink.
+    pub fn clicks_bot(&self) -> i64 {
+        self.clicks_bot
+    }
+
+    /// The amount of times this shortlink was fetched to render a link preview.
+    pub fn clicks_preview(&self) -> i64 {
+        self.clicks_preview
+    }
+
+    pub fn preview_title(&self) -> Option<&str> {
+        self.preview_title.as_deref()
+    }
+
+    pub fn preview_description(&self) -> Option<&str> {
+        self.preview_description.as_deref()
+    }
+
+    pub fn preview_image(&self) -> Option<&str> {
+        self.preview_image.as_deref()
+    }
+
+    /// Whether or not the owner customized how this shortlink is unfurled in chat apps.
+    pub fn has_preview(&self) -> bool {
+        self.preview_title.is_some()
+            || self.preview_description.is_some()
+            || self.preview_image.is_some()
+    }
+
+    /// The title of the destination page, fetched or overridden by the owner.
+    pub fn page_title(&self) -> Option<&str> {
+        self.page_title.as_deref()
+    }
+
+    pub fn page_favicon(&self) -> Option<&str> {
+        self.page_favicon.as_deref()
+    }
+
+    pub fn page_meta_override(&self) -> bool {
+        self.page_meta_override
+    }
+
+    /// The title given to this shortlink by its owner.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// A free-text note of the owner, e.g. to remember why this shortlink exists.
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    /// Whether or not the shortlink matches the (case insensitive) search query,
+    /// which is looked up in its hash, links, titles and note.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || [
+                Some(self.link_hash.as_str()),
+                Some(self.link_long.as_str()),
+                self.link_canonical.as_deref(),
+                self.page_title.as_deref(),
+                self.title.as_deref(),
+                self.note.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|value| value.to_lowercase().contains(&query))
+    }
+
+    /// Canonicalize a long link, such that equivalent links are stored the same way.
+    ///
+    /// Parsing the url already lowercases its scheme and host, drops default ports
+    /// and resolves dot segments. On top of that empty queries and fragments are dropped,
+    /// as well as known tracking parameters if so desired.
+    pub fn canonicalize_url(url: &Url, strip_tracking: bool) -> Url {
+        let mut url = url.clone();
+        if url.fragment() == Some("") {
+            url.set_fragment(None);
+        }
+        // filter the raw query, as to not change the encoding of the remaining parameters
+        let query = url.query().map(|query| {
+            query
+                .split('&')
+                .filter(|param| {
+                    let key = param.split('=').next().unwrap_or_default();
+                    !param.is_empty()
+                        && !(strip_tracking
+                            && (key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)))
+                })
+                .collect::<Vec<_>>()
+                .join("&")
+        });
+        url.set_query(query.as_deref().filter(|query| !query.is_empty()));
+        url
+    }
+
+    /// Render the shortlink, using the given scheme and host of the default namespace,
+    /// unless the shortlink lives on a custom domain, which is always served over https.
     pub fn link_short(&self, scheme: &str, host: &str) -> String {
-        format!("{}://{}/{}", scheme, host, self.link_hash)
+        match &self.domain {
+            Some(domain) => format!("https://{}/{}", domain, self.link_hash),
+            None => format!("{}://{}/{}", scheme, host, self.link_hash),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortlink_short_default_domain() {
+        let shortlink = Shortlink::new(
+            "https://example.com".to_owned(),
+            "https://example.com/".to_owned(),
+            "owner".to_owned(),
+            None,
+            None,
+        );
+        assert_eq!(
+            shortlink.link_short("http", "localhost:8000"),
+            format!("http://localhost:8000/{}", shortlink.link_hash())
+        );
+    }
+
+    #[test]
+    fn test_shortlink_short_custom_domain() {
+        let shortlink = Shortlink::new(
+            "https://example.com".to_owned(),
+            "https://example.com/".to_owned(),
+            "owner".to_owned(),
+            None,
+            Some("go.example.com".to_owned()),
+        );
+        assert_eq!(
+            shortlink.link_short("https", "bckt.xyz"),
+            format!("https://go.example.com/{}", shortlink.link_hash())
+        );
+    }
+
+    fn canonicalize(url: &str, strip_tracking: bool) -> String {
+        Shortlink::canonicalize_url(&Url::parse(url).unwrap(), strip_tracking).to_string()
+    }
+
+    #[test]
+    fn test_canonicalize_url() {
+        assert_eq!(
+            canonicalize("HTTPS://Example.com:443/a/../b", false),
+            "https://example.com/b"
+        );
+        assert_eq!(
+            canonicalize("http://EXAMPLE.com:80/./b/", false),
+            "http://example.com/b/"
+        );
+        assert_eq!(
+            canonicalize("https://example.com/b#", false),
+            "https://example.com/b"
+        );
+        assert_eq!(
+            canonicalize("https://example.com/b?", false),
+            "https://example.com/b"
+        );
+        assert_eq!(
+            canonicalize("https://example.com:8443/b?q=a+b&&x=%20#top", false),
+            "https://example.com:8443/b?q=a+b&x=%20#top"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_url_tracking() {
+        let url = "https://example.com/b?utm_source=news&id=1&fbclid=abc&utm_medium=mail";
+        assert_eq!(canonicalize(url, false), url);
+        assert_eq!(canonicalize(url, true), "https://example.com/b?id=1");
+        assert_eq!(
+            canonicalize("https://example.com/?gclid=x&utm_campaign=y", true),
+            "https://example.com/"
+        );
+    }
+
+    #[test]
+    fn test_shortlink_matches() {
+        let mut shortlink = Shortlink::new(
+            "https://example.com/docs?utm_source=x".to_owned(),
+            "https://example.com/docs".to_owned(),
+            "owner".to_owned(),
+            None,
+            None,
+        );
+        shortlink.title = Some("Onboarding Docs".to_owned());
+        shortlink.note = Some("Shared in the welcome mail of new hires".to_owned());
+        assert!(shortlink.matches(""));
+        assert!(shortlink.matches("onboarding"));
+        assert!(shortlink.matches(" WELCOME mail "));
+        assert!(shortlink.matches("utm_source"));
+        assert!(shortlink.matches(shortlink.link_hash()));
+        assert!(!shortlink.matches("payroll"));
     }
 }
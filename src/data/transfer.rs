@@ -0,0 +1,103 @@
+use super::Shortlink;
+
+/// A pending transfer of a personal shortlink from its owner to another user.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LinkTransfer {
+    id: i32,
+    link_hash: String,
+    link_long: String,
+    sender_display: String,
+    recipient_display: String,
+}
+
+impl LinkTransfer {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn link_hash(&self) -> &str {
+        &self.link_hash
+    }
+
+    pub fn link_long(&self) -> &str {
+        &self.link_long
+    }
+
+    pub fn sender_display(&self) -> &str {
+        &self.sender_display
+    }
+
+    pub fn recipient_display(&self) -> &str {
+        &self.recipient_display
+    }
+
+    /// Check whether the sender may offer the shortlink to the recipient,
+    /// returning the reason why not otherwise.
+    ///
+    /// Shortlinks on a custom domain stay with the owner of the domain,
+    /// as they would be deleted together with it.
+    pub fn validate(
+        shortlink: &Shortlink,
+        sender_email_hash: &str,
+        sender_email: &str,
+        recipient: &str,
+    ) -> Result<(), &'static str> {
+        if recipient.is_empty() || !recipient.contains('@') || recipient == sender_email {
+            return Err(
+                "A valid email address of another user is required to transfer a shortlink.",
+            );
+        }
+        if shortlink.owner_email() != sender_email_hash || shortlink.workspace_id().is_some() {
+            return Err("Only your personal shortlinks can be transferred.");
+        }
+        if shortlink.domain().is_some() {
+            return Err("Shortlinks on a custom domain cannot be transferred.");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shortlink(owner: &str, workspace: Option<i32>, domain: Option<&str>) -> Shortlink {
+        Shortlink::new(
+            "https://example.com".to_owned(),
+            "https://example.com/".to_owned(),
+            owner.to_owned(),
+            workspace,
+            domain.map(str::to_owned),
+        )
+    }
+
+    #[test]
+    fn test_transfer_validate_recipient() {
+        let link = shortlink("hash", None, None);
+        assert!(LinkTransfer::validate(&link, "hash", "me@example.com", "you@example.com").is_ok());
+        for recipient in ["", "not-an-email", "me@example.com"] {
+            assert!(
+                LinkTransfer::validate(&link, "hash", "me@example.com", recipient).is_err(),
+                "{recipient}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_transfer_validate_link() {
+        // only personal shortlinks of the sender
+        let link = shortlink("other", None, None);
+        assert!(
+            LinkTransfer::validate(&link, "hash", "me@example.com", "you@example.com").is_err()
+        );
+        let link = shortlink("hash", Some(1), None);
+        assert!(
+            LinkTransfer::validate(&link, "hash", "me@example.com", "you@example.com").is_err()
+        );
+        // ...which do not live on a custom domain of the sender
+        let link = shortlink("hash", None, Some("go.example.com"));
+        assert!(
+            LinkTransfer::validate(&link, "hash", "me@example.com", "you@example.com").is_err()
+        );
+    }
+}
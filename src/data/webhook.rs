@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+
+/// A webhook, notified of clicks on a single shortlink,
+/// or on all personal shortlinks of its owner if no shortlink is given.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Webhook {
+    id: i32,
+    url: String,
+    secret: String,
+    link_hash: Option<String>,
+    domain: Option<String>,
+}
+
+impl Webhook {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The secret used to sign the deliveries of this webhook.
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    pub fn link_hash(&self) -> Option<&str> {
+        self.link_hash.as_deref()
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+}
+
+/// A single attempt to deliver an event to a webhook.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WebhookDelivery {
+    webhook_id: i32,
+    event_id: String,
+    attempt: i32,
+    status: Option<i32>,
+    error: Option<String>,
+    delivered_at: DateTime<Utc>,
+}
+
+impl WebhookDelivery {
+    pub fn webhook_id(&self) -> i32 {
+        self.webhook_id
+    }
+
+    pub fn event_id(&self) -> &str {
+        &self.event_id
+    }
+
+    pub fn attempt(&self) -> i32 {
+        self.attempt
+    }
+
+    /// The http status returned by the webhook, if it could be reached.
+    pub fn status(&self) -> Option<i32> {
+        self.status
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status
+            .map(|status| (200..300).contains(&status))
+            .unwrap_or_default()
+    }
+
+    pub fn delivered_at(&self) -> String {
+        self.delivered_at
+            .format("%Y-%m-%d %H:%M:%S UTC")
+            .to_string()
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkspaceRole {
+    Owner,
+    Editor,
+    Viewer,
+}
+
+impl WorkspaceRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkspaceRole::Owner => "owner",
+            WorkspaceRole::Editor => "editor",
+            WorkspaceRole::Viewer => "viewer",
+        }
+    }
+
+    /// Owners and editors can create and delete the shortlinks of a workspace.
+    pub fn can_edit_links(&self) -> bool {
+        matches!(self, WorkspaceRole::Owner | WorkspaceRole::Editor)
+    }
+
+    /// Only owners can manage the members of a workspace.
+    pub fn can_manage(&self) -> bool {
+        matches!(self, WorkspaceRole::Owner)
+    }
+}
+
+impl fmt::Display for WorkspaceRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for WorkspaceRole {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.as_str().parse()
+    }
+}
+
+impl std::str::FromStr for WorkspaceRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "owner" => Ok(WorkspaceRole::Owner),
+            "editor" => Ok(WorkspaceRole::Editor),
+            "viewer" => Ok(WorkspaceRole::Viewer),
+            other => Err(format!("unknown workspace role '{other}'")),
+        }
+    }
+}
+
+/// A workspace as seen by one of its members.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Workspace {
+    id: i32,
+    name: String,
+    #[sqlx(try_from = "String")]
+    role: WorkspaceRole,
+}
+
+impl Workspace {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn role(&self) -> WorkspaceRole {
+        self.role
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WorkspaceMember {
+    member_email: String,
+    member_display: String,
+    #[sqlx(try_from = "String")]
+    role: WorkspaceRole,
+}
+
+impl WorkspaceMember {
+    pub fn member_email(&self) -> &str {
+        &self.member_email
+    }
+
+    pub fn member_display(&self) -> &str {
+        &self.member_display
+    }
+
+    pub fn role(&self) -> WorkspaceRole {
+        self.role
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_role_parse() {
+        assert_eq!("owner".parse::<WorkspaceRole>(), Ok(WorkspaceRole::Owner));
+        assert_eq!(
+            " Editor ".parse::<WorkspaceRole>(),
+            Ok(WorkspaceRole::Editor)
+        );
+        assert_eq!("viewer".parse::<WorkspaceRole>(), Ok(WorkspaceRole::Viewer));
+        assert!("admin".parse::<WorkspaceRole>().is_err());
+    }
+
+    #[test]
+    fn test_workspace_role_permissions() {
+        assert!(WorkspaceRole::Owner.can_manage());
+        assert!(WorkspaceRole::Owner.can_edit_links());
+        assert!(!WorkspaceRole::Editor.can_manage());
+        assert!(WorkspaceRole::Editor.can_edit_links());
+        assert!(!WorkspaceRole::Viewer.can_manage());
+        assert!(!WorkspaceRole::Viewer.can_edit_links());
+    }
+}
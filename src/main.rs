@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use shuttle_secrets::SecretStore;
 use sqlx::{Executor, PgPool};
@@ -16,15 +16,95 @@ async fn axum(
         .await
         .expect("migrate Postgres database");
 
+    let mailer = services::Mailer::new(secret_store.get("SENDGRID_API_KEY").unwrap());
+
     let auth = Arc::new(services::Auth::new(
         secret_store.get("AUTH_PRIVATE_KEY").unwrap(),
         secret_store.get("AUTHORIZED_EMAILS").unwrap(),
-        secret_store.get("SENDGRID_API_KEY").unwrap(),
+        mailer.clone(),
     ));
 
     let storage = services::Storage::new(pool);
 
-    let state = router::State { auth, storage };
+    // files of secrets are stored in an S3-compatible bucket if configured,
+    // and on the local filesystem otherwise
+    let blobs: Arc<dyn services::BlobStore> = match secret_store.get("S3_ENDPOINT") {
+        Some(endpoint) => Arc::new(services::S3BlobStore::new(
+            reqwest::Url::parse(&endpoint).expect("parse S3_ENDPOINT"),
+            secret_store
+                .get("S3_BUCKET")
+                .unwrap_or_else(|| "bckt".to_owned()),
+            secret_store
+                .get("S3_REGION")
+                .unwrap_or_else(|| "us-east-1".to_owned()),
+            secret_store.get("S3_ACCESS_KEY").unwrap(),
+            secret_store.get("S3_SECRET_KEY").unwrap(),
+        )),
+        None => Arc::new(services::LocalBlobStore::new(
+            secret_store
+                .get("BLOB_DIR")
+                .unwrap_or_else(|| "blobs".to_owned()),
+        )),
+    };
+    services::secret::spawn_purge(storage.clone(), blobs.clone(), Duration::from_secs(5 * 60));
+
+    let page_fetcher = Arc::new(services::HttpPageFetcher::default());
+    let shortener_resolver = Arc::new(services::HttpShortenerResolver::default());
+    let txt_resolver = Arc::new(services::DohTxtResolver::default());
+    let webhooks =
+        services::WebhookDispatcher::spawn(Arc::new(storage.clone()), 5, Duration::from_secs(2));
+    services::webhook::spawn_purge(storage.clone(), Duration::from_secs(60 * 60));
+
+    let public_hosts = secret_store
+        .get("PUBLIC_HOSTS")
+        .unwrap_or_else(|| "bckt.xyz,www.bckt.xyz".to_owned())
+        .split(',')
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect::<Vec<String>>();
+
+    // render the weekly digests without sending them, e.g. while developing
+    let digest_dry_run = secret_store
+        .get("DIGEST_DRY_RUN")
+        .map(|dry_run| dry_run == "true")
+        .unwrap_or_default();
+    let digests = Arc::new(services::Digests::new(
+        storage.clone(),
+        mailer.clone(),
+        Arc::new(services::HttpLinkChecker::default()),
+        public_hosts
+            .first()
+            .cloned()
+            .unwrap_or_else(|| "bckt.xyz".to_owned()),
+        digest_dry_run,
+    ));
+    digests.clone().spawn();
+
+    // shuttle runs the service behind a single proxy
+    let trusted_proxy_hops = secret_store
+        .get("TRUSTED_PROXY_HOPS")
+        .map(|hops| hops.parse().expect("parse TRUSTED_PROXY_HOPS"))
+        .unwrap_or(1);
+
+    // the metrics are only served to scrapers knowing this token
+    let metrics_token = secret_store
+        .get("METRICS_TOKEN")
+        .filter(|token| !token.is_empty());
+
+    let state = router::State {
+        auth,
+        storage,
+        page_fetcher,
+        shortener_resolver,
+        txt_resolver,
+        webhooks,
+        blobs,
+        digests,
+        mailer,
+        public_hosts,
+        metrics_token,
+        trusted_proxy_hops,
+    };
     let router = router::new(state);
 
     tracing::debug!("starting axum router");
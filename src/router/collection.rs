@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
+use axum::{
+    extract::{Host, State},
+    http::StatusCode,
+    response::Redirect,
+    Form,
+};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+
+use crate::data::{Collection, CollectionItem, Shortlink};
+
+#[derive(Template)]
+#[template(path = "../templates/content/collection.html")]
+pub struct PublicTemplate {
+    pub collection: Collection,
+    pub items: Vec<CollectionItem>,
+    pub scheme: String,
+    pub host: String,
+}
+
+/// Render a collection publicly, served at `/@{slug}`.
+pub async fn get_public(state: &crate::router::State, host: &str, slug: &str) -> Response {
+    match state.storage.get_collection(slug).await {
+        Some(collection) => {
+            let items = state.storage.get_collection_items(collection.id()).await;
+            let host = super::shared::default_host(state, host).await;
+            PublicTemplate {
+                collection,
+                items,
+                scheme: super::shared::scheme_for_host(&host).to_owned(),
+                host,
+            }
+            .into_response()
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            super::shared::ErrorTemplate {
+                title: "Not Found".to_string(),
+                message: format!("The collection '@{slug}' does not exist."),
+                back_path: "/".to_string(),
+            },
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/collection_manage.html")]
+pub struct GetTemplate {
+    pub email: String,
+    pub collections: Vec<(Collection, Vec<CollectionItem>)>,
+    pub shortlinks: Vec<Shortlink>,
+    pub scheme: String,
+    pub host: String,
+}
+
+pub async fn get(
+    State(state): State<Arc<crate::router::State>>,
+    Host(host): Host,
+    cookies: Cookies,
+) -> Response {
+    if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
+        if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
+            let mut collections = Vec::new();
+            for collection in state
+                .storage
+                .get_collections_for_owner(identity.email_hash())
+                .await
+            {
+                let items = state.storage.get_collection_items(collection.id()).await;
+                collections.push((collection, items));
+            }
+            let shortlinks = state
+                .storage
+                .get_shortlinks_for_owner(identity.email_hash())
+                .await;
+            let host = super::shared::default_host(&state, &host).await;
+            return GetTemplate {
+                email: identity.email().to_owned(),
+                collections,
+                shortlinks,
+                scheme: super::shared::scheme_for_host(&host).to_owned(),
+                host,
+            }
+            .into_response();
+        }
+    }
+    Redirect::temporary("/").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct PostParams {
+    action: String,
+    collection: Option<i32>,
+    item: Option<i32>,
+    slug: Option<String>,
+    /// The shortlink to add, as `hash` or `domain/hash`.
+    link: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+}
+
+pub async fn post(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Form(params): Form<PostParams>,
+) -> Response {
+    let identity = match cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+    {
+        Some(identity) => identity,
+        None => {
+            return error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "You are not authorized for managing collections.".to_string(),
+            )
+        }
+    };
+
+    let title = params.title.unwrap_or_default().trim().to_owned();
+    let description = params.description.unwrap_or_default().trim().to_owned();
+    if title.len() > 128 {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Invalid Title",
+            "A title can be at most 128 characters long.".to_string(),
+        );
+    }
+
+    let result = match (params.action.as_str(), params.collection, params.item) {
+        ("create", _, _) => {
+            let slug = params.slug.unwrap_or_default().trim().to_lowercase();
+            if !Collection::is_valid_slug(&slug) {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid Collection Name",
+                    "A collection name is between 3 and 32 characters long and only uses lowercase letters, digits, dashes and underscores.".to_string(),
+                );
+            }
+            let title = if title.is_empty() {
+                slug.clone()
+            } else {
+                title
+            };
+            state
+                .storage
+                .add_collection(&slug, identity.email_hash(), &title, &description)
+                .await
+        }
+        ("delete", Some(collection), _) => {
+            state
+                .storage
+                .delete_collection(collection, identity.email_hash())
+                .await
+        }
+        ("add_item", Some(collection), _) => {
+            let link = params.link.unwrap_or_default();
+            let (link_hash, link_domain) = match link.rsplit_once('/') {
+                Some((domain, hash)) => (hash, Some(domain)),
+                None => (link.as_str(), None),
+            };
+            if title.is_empty() {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "Title Missing",
+                    "Each item of a collection requires a title.".to_string(),
+                );
+            }
+            state
+                .storage
+                .add_collection_item(
+                    collection,
+                    identity.email_hash(),
+                    link_hash,
+                    link_domain,
+                    &title,
+                    &description,
+                )
+                .await
+        }
+        ("move_up", _, Some(item)) | ("move_down", _, Some(item)) => {
+            state
+                .storage
+                .move_collection_item(item, identity.email_hash(), params.action == "move_up")
+                .await
+        }
+        ("remove_item", _, Some(item)) => {
+            state
+                .storage
+                .delete_collection_item(item, identity.email_hash())
+                .await
+        }
+        (other, _, _) => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "Bad Action",
+                format!("Invalid action {other}."),
+            )
+        }
+    };
+
+    match result {
+        Ok(()) => Redirect::to("/link/collection").into_response(),
+        Err(err) => error(
+            StatusCode::BAD_REQUEST,
+            "Failed to Update Collection",
+            format!("The collection could not be updated: {err}."),
+        ),
+    }
+}
+
+fn error(status: StatusCode, title: &str, message: String) -> Response {
+    (
+        status,
+        super::shared::ErrorTemplate {
+            title: title.to_string(),
+            message,
+            back_path: "/link/collection".to_string(),
+        },
+    )
+        .into_response()
+}
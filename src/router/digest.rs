@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
+use axum::{extract::State, http::StatusCode, response::Redirect, Form};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+
+use crate::services::digest::{Digest, DigestHtmlTemplate};
+
+#[derive(Template)]
+#[template(path = "../templates/content/digest.html")]
+pub struct GetTemplate {
+    pub email: String,
+    pub digest_email: Option<String>,
+    pub digest: Digest,
+}
+
+#[derive(Deserialize)]
+pub struct GetParams {
+    preview: Option<bool>,
+}
+
+/// Show the digest preference of the user, and what the digest would look like this week,
+/// or render the digest email itself when previewing it, without sending it.
+pub async fn get(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Form(params): Form<GetParams>,
+) -> Response {
+    if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
+        if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
+            // the destinations are only checked when the digest is actually sent
+            let digest = state.digests.collect(identity.email_hash(), false).await;
+            if params.preview.unwrap_or_default() {
+                return DigestHtmlTemplate { digest: &digest }.into_response();
+            }
+            return GetTemplate {
+                email: identity.email().to_owned(),
+                digest_email: state.storage.get_digest_email(identity.email_hash()).await,
+                digest,
+            }
+            .into_response();
+        }
+    }
+    Redirect::temporary("/").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct PostParams {
+    action: String,
+}
+
+pub async fn post(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Form(params): Form<PostParams>,
+) -> Response {
+    let identity = match cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+    {
+        Some(identity) => identity,
+        None => {
+            return error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "You are not authorized for managing your digest.".to_string(),
+            )
+        }
+    };
+
+    // only the email of the user is stored, and only once they opt in
+    let digest_email = match params.action.as_str() {
+        "subscribe" => Some(identity.email()),
+        "unsubscribe" => None,
+        other => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "Bad Action",
+                format!("Invalid action {other}."),
+            )
+        }
+    };
+
+    match state
+        .storage
+        .set_digest_email(identity.email_hash(), digest_email)
+        .await
+    {
+        Ok(()) => Redirect::to("/link/digest").into_response(),
+        Err(err) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to Update Digest",
+            format!("Your digest preference could not be updated: {err}."),
+        ),
+    }
+}
+
+fn error(status: StatusCode, title: &str, message: String) -> Response {
+    (
+        status,
+        super::shared::ErrorTemplate {
+            title: title.to_string(),
+            message,
+            back_path: "/link/digest".to_string(),
+        },
+    )
+        .into_response()
+}
@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
+use axum::{
+    extract::{Host, State},
+    http::StatusCode,
+    response::Redirect,
+    Form,
+};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+
+use crate::data::{Domain, Workspace};
+
+#[derive(Template)]
+#[template(path = "../templates/content/domain.html")]
+pub struct GetTemplate {
+    pub email: String,
+    pub host: String,
+    pub domains: Vec<Domain>,
+    pub workspaces: Vec<(Workspace, Vec<Domain>)>,
+}
+
+pub async fn get(
+    State(state): State<Arc<crate::router::State>>,
+    Host(host): Host,
+    cookies: Cookies,
+) -> Response {
+    if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
+        if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
+            let domains = state
+                .storage
+                .get_domains_for_owner(identity.email_hash())
+                .await;
+            let mut workspaces = Vec::new();
+            for workspace in state
+                .storage
+                .get_workspaces_for_member(identity.email_hash())
+                .await
+            {
+                let domains = state
+                    .storage
+                    .get_domains_for_workspace(workspace.id())
+                    .await;
+                workspaces.push((workspace, domains));
+            }
+            return GetTemplate {
+                email: identity.email().to_owned(),
+                host: Domain::normalize_host(&host),
+                domains,
+                workspaces,
+            }
+            .into_response();
+        }
+    }
+    Redirect::temporary("/").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct PostParams {
+    action: String,
+    host: String,
+    workspace: Option<String>,
+}
+
+pub async fn post(
+    State(state): State<Arc<crate::router::State>>,
+    Host(request_host): Host,
+    cookies: Cookies,
+    Form(params): Form<PostParams>,
+) -> Response {
+    let identity = match cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+    {
+        Some(identity) => identity,
+        None => {
+            return error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "You are not authorized for managing domains.".to_string(),
+            )
+        }
+    };
+
+    let host = Domain::normalize_host(&params.host);
+    let result = match params.action.as_str() {
+        "create" => {
+            // only allow valid host names with at least a second level domain
+            let valid = reqwest::Url::parse(&format!("https://{host}"))
+                .ok()
+                .and_then(|url| url.domain().map(|domain| domain == host))
+                .unwrap_or_default();
+            if !valid || host.split('.').count() < 2 {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid Domain",
+                    format!("The domain '{host}' is not a valid host name."),
+                );
+            }
+            if host == Domain::normalize_host(&request_host)
+                || Domain::is_reserved_host(&host)
+                || state.public_hosts.contains(&host)
+            {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid Domain",
+                    format!("The domain '{host}' is reserved."),
+                );
+            }
+            let workspace = match params.workspace.filter(|workspace| !workspace.is_empty()) {
+                Some(workspace) => match workspace.parse::<i32>() {
+                    Ok(workspace) => Some(workspace),
+                    Err(_) => {
+                        return error(
+                            StatusCode::BAD_REQUEST,
+                            "Invalid Workspace",
+                            format!("The workspace '{workspace}' is not valid."),
+                        )
+                    }
+                },
+                None => None,
+            };
+            match workspace {
+                Some(workspace) => {
+                    let role = state
+                        .storage
+                        .get_workspace_role(workspace, identity.email_hash())
+                        .await;
+                    if !role.map(|role| role.can_manage()).unwrap_or_default() {
+                        return error(
+                            StatusCode::FORBIDDEN,
+                            "Forbidden",
+                            "Only owners of a workspace can add domains to it.".to_string(),
+                        );
+                    }
+                    state.storage.add_domain(&host, None, Some(workspace)).await
+                }
+                None => {
+                    state
+                        .storage
+                        .add_domain(&host, Some(identity.email_hash()), None)
+                        .await
+                }
+            }
+        }
+        "verify" => {
+            let domain = match state
+                .storage
+                .get_managed_domain(&host, identity.email_hash())
+                .await
+            {
+                Some(domain) => domain,
+                None => {
+                    return error(
+                        StatusCode::NOT_FOUND,
+                        "Domain Missing",
+                        format!("No domain '{host}' exists that you are allowed to verify."),
+                    )
+                }
+            };
+            if domain.is_verified() {
+                return Redirect::to("/domain").into_response();
+            }
+            let name = domain.verification_name();
+            match state.txt_resolver.txt_records(&name).await {
+                Ok(records) if domain.is_verified_by(&records) => {
+                    state.storage.verify_domain(&domain).await
+                }
+                Ok(_) => Err(format!(
+                    "no TXT record '{name}' with the value '{}' was found, \
+                    note that it can take a while before changes to DNS records are visible",
+                    domain.verification_value()
+                )),
+                Err(err) => Err(format!(
+                    "the TXT records of '{name}' could not be looked up: {err}"
+                )),
+            }
+        }
+        "delete" => {
+            state
+                .storage
+                .delete_domain(&host, identity.email_hash())
+                .await
+        }
+        other => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "Bad Action",
+                format!("Invalid action {other}."),
+            )
+        }
+    };
+
+    match result {
+        Ok(()) => Redirect::to("/domain").into_response(),
+        Err(err) => error(
+            StatusCode::BAD_REQUEST,
+            "Failed to Update Domain",
+            format!("The domain '{host}' could not be updated: {err}."),
+        ),
+    }
+}
+
+fn error(status: StatusCode, title: &str, message: String) -> Response {
+    (
+        status,
+        super::shared::ErrorTemplate {
+            title: title.to_string(),
+            message,
+            back_path: "/domain".to_string(),
+        },
+    )
+        .into_response()
+}
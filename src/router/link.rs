@@ -4,28 +4,92 @@ use askama::Template;
 use askama_axum::{IntoResponse, Response};
 use axum::{
     extract::{Host, State},
-    http::StatusCode,
+    http::{header::CONTENT_DISPOSITION, StatusCode},
     response::Redirect,
-    Form,
+    Form, Json,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tower_cookies::Cookies;
 
-use crate::data::Shortlink;
+use crate::{
+    data::{Domain, LinkTransfer, Shortlink, Workspace},
+    services::{shortener::is_known_shortener, PageMeta},
+};
 
 #[derive(Template)]
 #[template(path = "../templates/content/link.html")]
 pub struct GetTemplate {
     pub email: String,
     pub long: Option<String>,
+    pub query: String,
     pub shortlinks: Vec<Shortlink>,
+    pub workspaces: Vec<Workspace>,
+    pub workspace: Option<Workspace>,
+    pub incoming_transfers: Vec<LinkTransfer>,
+    pub outgoing_transfers: Vec<LinkTransfer>,
+    pub domains: Vec<Domain>,
     pub scheme: String,
     pub host: String,
 }
 
+impl GetTemplate {
+    fn display_url(&self, url: &str) -> String {
+        crate::services::idn::display_url(url)
+    }
+
+    fn can_edit_links(&self) -> bool {
+        self.workspace
+            .as_ref()
+            .map(|workspace| workspace.role().can_edit_links())
+            .unwrap_or(true)
+    }
+}
+
 #[derive(Deserialize)]
 pub struct GetParams {
     long: Option<String>,
+    workspace: Option<i32>,
+    q: Option<String>,
+}
+
+/// Get the shortlinks of the given workspace, or the personal ones if none is given,
+/// matching the search query, if any.
+async fn get_shortlinks(
+    state: &crate::router::State,
+    email_hash: &str,
+    workspaces: &[Workspace],
+    workspace: Option<i32>,
+    query: &str,
+) -> Result<(Option<Workspace>, Vec<Shortlink>), Response> {
+    let (workspace, shortlinks) = match workspace {
+        Some(id) => {
+            let workspace = match workspaces.iter().find(|w| w.id() == id) {
+                Some(workspace) => workspace.clone(),
+                None => {
+                    return Err((
+                        StatusCode::FORBIDDEN,
+                        super::shared::ErrorTemplate {
+                            title: "Forbidden".to_string(),
+                            message: "You are not a member of this workspace.".to_string(),
+                            back_path: "/link".to_string(),
+                        },
+                    )
+                        .into_response())
+                }
+            };
+            let shortlinks = state.storage.get_shortlinks_for_workspace(id).await;
+            (Some(workspace), shortlinks)
+        }
+        None => {
+            let shortlinks = state.storage.get_shortlinks_for_owner(email_hash).await;
+            (None, shortlinks)
+        }
+    };
+    let shortlinks = shortlinks
+        .into_iter()
+        .filter(|shortlink| shortlink.matches(query))
+        .collect();
+    Ok((workspace, shortlinks))
 }
 
 pub async fn get(
@@ -36,21 +100,68 @@ pub async fn get(
 ) -> Response {
     if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
         if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
-            let shortlinks = state
+            let workspaces = state
                 .storage
-                .get_shortlinks_for_owner(identity.email_hash())
+                .get_workspaces_for_member(identity.email_hash())
                 .await;
+            let query = params.q.unwrap_or_default();
+            let (workspace, shortlinks) = match get_shortlinks(
+                &state,
+                identity.email_hash(),
+                &workspaces,
+                params.workspace,
+                &query,
+            )
+            .await
+            {
+                Ok(result) => result,
+                Err(response) => return response,
+            };
+            let (incoming_transfers, outgoing_transfers) = if workspace.is_none() {
+                (
+                    state
+                        .storage
+                        .get_incoming_link_transfers(identity.email_hash())
+                        .await,
+                    state
+                        .storage
+                        .get_outgoing_link_transfers(identity.email_hash())
+                        .await,
+                )
+            } else {
+                (Vec::new(), Vec::new())
+            };
+            let domains = match &workspace {
+                Some(workspace) => {
+                    state
+                        .storage
+                        .get_domains_for_workspace(workspace.id())
+                        .await
+                }
+                None => {
+                    state
+                        .storage
+                        .get_domains_for_owner(identity.email_hash())
+                        .await
+                }
+            }
+            .into_iter()
+            // shortlinks can only be created for domains once they are verified
+            .filter(Domain::is_verified)
+            .collect();
+            let default_host = super::shared::default_host(&state, &host).await;
             return GetTemplate {
                 email: identity.email().to_owned(),
                 long: params.long,
+                query,
                 shortlinks,
-                scheme: if host.to_lowercase().contains("bckt.xyz") {
-                    "https"
-                } else {
-                    "http"
-                }
-                .to_owned(),
-                host: host.to_owned(),
+                workspaces,
+                workspace,
+                incoming_transfers,
+                outgoing_transfers,
+                domains,
+                scheme: super::shared::scheme_for_host(&default_host).to_owned(),
+                host: default_host,
             }
             .into_response();
         }
@@ -58,19 +169,194 @@ pub async fn get(
     Redirect::temporary("/").into_response()
 }
 
+/// A shortlink as it is exported by its owner.
+#[derive(Serialize)]
+struct ExportedShortlink<'a> {
+    short: String,
+    long: &'a str,
+    canonical: &'a str,
+    title: Option<&'a str>,
+    note: Option<&'a str>,
+    page_title: Option<&'a str>,
+    clicks: i64,
+    clicks_bot: i64,
+    clicks_preview: i64,
+}
+
+/// Export the (matching) shortlinks of the user or a workspace as JSON.
+pub async fn get_export(
+    State(state): State<Arc<crate::router::State>>,
+    Host(host): Host,
+    cookies: Cookies,
+    Form(params): Form<GetParams>,
+) -> Response {
+    if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
+        if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
+            let workspaces = state
+                .storage
+                .get_workspaces_for_member(identity.email_hash())
+                .await;
+            let shortlinks = match get_shortlinks(
+                &state,
+                identity.email_hash(),
+                &workspaces,
+                params.workspace,
+                params.q.as_deref().unwrap_or_default(),
+            )
+            .await
+            {
+                Ok((_, shortlinks)) => shortlinks,
+                Err(response) => return response,
+            };
+            let host = super::shared::default_host(&state, &host).await;
+            let scheme = super::shared::scheme_for_host(&host);
+            let exported: Vec<_> = shortlinks
+                .iter()
+                .map(|shortlink| ExportedShortlink {
+                    short: shortlink.link_short(scheme, &host),
+                    long: shortlink.link_long(),
+                    canonical: shortlink.link_canonical(),
+                    title: shortlink.title(),
+                    note: shortlink.note(),
+                    page_title: shortlink.page_title(),
+                    clicks: shortlink.clicks(),
+                    clicks_bot: shortlink.clicks_bot(),
+                    clicks_preview: shortlink.clicks_preview(),
+                })
+                .collect();
+            return (
+                [(
+                    CONTENT_DISPOSITION,
+                    "attachment; filename=\"bckt-shortlinks.json\"",
+                )],
+                Json(exported),
+            )
+                .into_response();
+        }
+    }
+    Redirect::temporary("/").into_response()
+}
+
 #[derive(Template)]
 #[template(path = "../templates/content/link_ok.html")]
 pub struct PostOkTemplate {
     pub email: String,
     pub long: String,
     pub short: String,
-    pub hash: String,
+    pub back_path: String,
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/link_confirm.html")]
+pub struct PostConfirmTemplate {
+    pub email: String,
+    pub warning: String,
+    pub long: String,
+    pub display: String,
+    pub encoded: String,
+    pub workspace: Option<i32>,
+    pub domain: Option<String>,
+    pub strip_tracking: bool,
+    pub back_path: String,
 }
 
 #[derive(Deserialize)]
 pub struct PostParams {
     value: String,
     action: String,
+    workspace: Option<i32>,
+    recipient: Option<String>,
+    domain: Option<String>,
+    strip_tracking: Option<String>,
+    confirm_homograph: Option<String>,
+    preview_title: Option<String>,
+    preview_description: Option<String>,
+    preview_image: Option<String>,
+    page_title: Option<String>,
+    page_favicon: Option<String>,
+    title: Option<String>,
+    note: Option<String>,
+}
+
+/// Whether or not the (punycode) domain is served by this service,
+/// in which case linking to it would create chains or even loops of shortlinks.
+async fn is_own_host(state: &crate::router::State, request_host: &str, domain: &str) -> bool {
+    let domain = Domain::normalize_host(domain.trim_end_matches('.'));
+    domain == Domain::normalize_host(request_host)
+        || state.public_hosts.contains(&domain)
+        || state.storage.get_domain(&domain).await.is_some()
+}
+
+/// Check whether or not a link may point to the destination, returning its (punycode) domain,
+/// or the reason why it is not allowed.
+async fn check_destination<'a>(
+    state: &crate::router::State,
+    request_host: &str,
+    url: &'a reqwest::Url,
+) -> Result<&'a str, String> {
+    // only allow http and https
+    if url.scheme() != "https" && url.scheme() != "http" {
+        return Err("Schema (protocol) is not supported.".to_owned());
+    }
+    // ...only allow domains, and not IP addresses
+    let domain = match url.domain() {
+        Some(domain) => domain,
+        None => return Err("No domain found.".to_owned()),
+    };
+    // ...only allow second level domains or higher
+    if domain.split('.').count() == 1 {
+        return Err("Bare top level domains are not allowed.".to_owned());
+    }
+    // ...do not allow links to shortlinks of our own
+    if is_own_host(state, request_host, domain).await {
+        return Err(format!(
+            "Links to shortlinks of {request_host} are not allowed."
+        ));
+    }
+    // ...only allow domains that are not blocked,
+    // which are matched in their punycode form as parsed by the url
+    if state.storage.is_domain_blocked(domain).await {
+        return Err("The domain is blocked.".to_owned());
+    }
+    Ok(domain)
+}
+
+/// Fetch the metadata of the destination page of a shortlink in the background.
+fn spawn_page_meta_fetch(state: &crate::router::State, shortlink: Shortlink, url: reqwest::Url) {
+    let fetcher = state.page_fetcher.clone();
+    let storage = state.storage.clone();
+    tokio::spawn(async move {
+        if let Some(meta) = fetcher.fetch(&url).await {
+            if let Err(err) = storage.set_shortlink_page_meta(&shortlink, &meta).await {
+                tracing::error!(
+                    "Failed to store page metadata for shortlink {}: {}",
+                    shortlink.link_hash(),
+                    err
+                );
+            }
+        }
+    });
+}
+
+fn back_path(workspace: Option<i32>) -> String {
+    match workspace {
+        Some(id) => format!("/link?workspace={id}"),
+        None => "/link".to_string(),
+    }
+}
+
+fn long_back_path(long: &str, workspace: Option<i32>) -> String {
+    match workspace {
+        Some(id) => format!("/link?workspace={id}&long={long}"),
+        None => format!("/link?long={long}"),
+    }
+}
+
+/// Trim an optional form value, treating an empty value as absent.
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
 }
 
 pub async fn post(
@@ -81,145 +367,602 @@ pub async fn post(
 ) -> impl IntoResponse {
     if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
         if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
+            let (email, email_hash) = (identity.email(), identity.email_hash());
             return match params.action.as_str() {
-                "create" => {
-                    let long = params.value;
-                    if long.is_empty() {
-                        return LinkPostResponse::BadRequest {
-                            reason: "URL is not specified.",
-                            long,
-                        };
-                    }
+                "create" => post_create(&state, &host, email, email_hash, params).await,
+                "delete" => post_delete(&state, email_hash, params).await,
+                "page_meta" => post_page_meta(&state, email_hash, params).await,
+                "note" => post_note(&state, email_hash, params).await,
+                "preview" => post_preview(&state, email_hash, params).await,
+                "transfer" => post_transfer(&state, email, email_hash, params).await,
+                "transfer_cancel" | "transfer_accept" => {
+                    post_transfer_update(&state, email_hash, params).await
+                }
+                other => LinkPostResponse::BadAction(other.to_string()),
+            };
+        }
+    }
+    LinkPostResponse::Forbidden
+}
 
-                    // default to https
-                    let long: String = if long.contains("://") {
-                        long.clone()
-                    } else {
-                        format!("https://{}", long)
-                    };
-
-                    // validate url
-                    let url = match reqwest::Url::parse(&long) {
-                        Ok(url) => url,
-                        Err(_) => {
-                            return LinkPostResponse::BadRequest {
-                                reason: "URL is invalid.",
-                                long,
-                            };
-                        }
-                    };
-
-                    // only allow http and https
-                    if url.scheme() != "https" && url.scheme() != "http" {
-                        return LinkPostResponse::BadRequest {
-                            reason: "Schema (protocol) is not supported.",
-                            long,
-                        };
-                    }
+async fn post_create(
+    state: &crate::router::State,
+    host: &str,
+    email: &str,
+    email_hash: &str,
+    params: PostParams,
+) -> LinkPostResponse {
+    let workspace = params.workspace;
+    let long = params.value;
+    if long.is_empty() {
+        return LinkPostResponse::BadRequest {
+            reason: "URL is not specified.".into(),
+            long,
+            workspace,
+        };
+    }
 
-                    // validate domains
-                    let domain = match url.domain() {
-                        Some(domain) => domain,
-                        None => {
-                            return LinkPostResponse::BadRequest {
-                                reason: "No domain found.",
-                                long,
-                            };
-                        }
-                    };
-                    // ...only allow second level domains or higher
-                    if domain.split('.').count() == 1 {
-                        return LinkPostResponse::BadRequest {
-                            reason: "Bare top level domains are not allowed.",
-                            long,
-                        };
-                    }
-                    // ...only allow domains that are not blocked
-                    if state.storage.is_domain_blocked(domain).await {
-                        return LinkPostResponse::BadRequest {
-                            reason: "The domain is blocked.",
-                            long,
-                        };
-                    }
+    // only owners and editors can create shortlinks within a workspace
+    if let Some(id) = workspace {
+        let role = state.storage.get_workspace_role(id, email_hash).await;
+        if !role.map(|role| role.can_edit_links()).unwrap_or_default() {
+            return LinkPostResponse::Forbidden;
+        }
+    }
+
+    // default to https
+    let long: String = if long.contains("://") {
+        long.clone()
+    } else {
+        format!("https://{}", long)
+    };
+
+    // validate url
+    let url = match reqwest::Url::parse(&long) {
+        Ok(url) => url,
+        Err(_) => {
+            return LinkPostResponse::BadRequest {
+                reason: "URL is invalid.".into(),
+                long,
+                workspace,
+            };
+        }
+    };
 
-                    // create shortlink
-                    let shortlink =
-                        Shortlink::new(url.to_string(), identity.email_hash().to_owned());
-
-                    // store shortlink
-                    if let Err(err) = state.storage.add_shortlink(&shortlink).await {
-                        tracing::error!(
-                            "Failed to store shortlink for long url {} by {}: {}",
-                            shortlink.owner_email(),
-                            shortlink.link_long(),
-                            err
-                        );
-                        return LinkPostResponse::Exception {
-                            reason: "Failed to store shortlink",
-                            long,
-                        };
-                    };
-
-                    return LinkPostResponse::Ok {
-                        email: identity.email().to_owned(),
-                        long: shortlink.link_long().to_string(),
-                        short: shortlink.link_short(
-                            if host.to_lowercase().contains("bckt.xyz") {
-                                "https"
-                            } else {
-                                "http"
-                            },
-                            &host,
-                        ),
-                        hash: shortlink.link_hash().to_owned(),
-                    };
+    // validate the destination
+    let domain = match check_destination(state, host, &url).await {
+        Ok(domain) => domain,
+        Err(reason) => {
+            return LinkPostResponse::BadRequest {
+                reason,
+                long,
+                workspace,
+            };
+        }
+    };
+    // ...resolve third-party shorteners, as these could hide where they go to,
+    // such that their destination is held to the same rules
+    if is_known_shortener(domain) {
+        let target = match state.shortener_resolver.resolve(&url).await {
+            Some(target) => target,
+            None => {
+                return LinkPostResponse::BadRequest {
+                    reason: "The destination of the shortened link could not be resolved.".into(),
+                    long,
+                    workspace,
+                };
+            }
+        };
+        let target_domain = match check_destination(state, host, &target).await {
+            Ok(target_domain) => target_domain,
+            Err(reason) => {
+                return LinkPostResponse::BadRequest {
+                    reason: format!(
+                        "The destination of the shortened link ({target}) is not allowed. {reason}"
+                    ),
+                    long,
+                    workspace,
+                };
+            }
+        };
+        if is_known_shortener(target_domain) {
+            return LinkPostResponse::BadRequest {
+                reason: "The shortened link is wrapped in too many shorteners.".into(),
+                long,
+                workspace,
+            };
+        }
+    }
+
+    // ...only allow custom domains owned by the user or the current workspace
+    let link_domain = match params.domain.filter(|domain| !domain.is_empty()) {
+        Some(domain) => match state.storage.get_domain(&domain).await {
+            Some(domain)
+                if (workspace.is_some() && domain.workspace_id() == workspace)
+                    || (workspace.is_none() && domain.owner_email() == Some(email_hash)) =>
+            {
+                Some(domain.host().to_owned())
+            }
+            _ => {
+                return LinkPostResponse::BadRequest {
+                    reason: "The custom domain is not available to you.".into(),
+                    long,
+                    workspace,
+                };
+            }
+        },
+        None => None,
+    };
+
+    // ...warn about domains which might impersonate another one
+    if params.confirm_homograph.is_none() {
+        if let Some(warning) = crate::services::idn::homograph_warning(domain) {
+            return LinkPostResponse::Other(
+                PostConfirmTemplate {
+                    email: email.to_owned(),
+                    warning: warning.to_string(),
+                    display: crate::services::idn::display_url(url.as_str()),
+                    encoded: url.to_string(),
+                    long: long.clone(),
+                    workspace,
+                    domain: link_domain,
+                    strip_tracking: params.strip_tracking.is_some(),
+                    back_path: long_back_path(&long, workspace),
                 }
-                "delete" => {
-                    let short = params.value;
-                    if short.is_empty() {
-                        return LinkPostResponse::ShortUrlMissing;
+                .into_response(),
+            );
+        }
+    }
+
+    // redirect to the canonical form, such that equivalent links behave the same
+    let url = Shortlink::canonicalize_url(&url, params.strip_tracking.is_some());
+
+    // create shortlink
+    let shortlink = Shortlink::new(
+        long.clone(),
+        url.to_string(),
+        email_hash.to_owned(),
+        workspace,
+        link_domain,
+    );
+
+    // store shortlink
+    if let Err(err) = state.storage.add_shortlink(&shortlink).await {
+        tracing::error!(
+            "Failed to store shortlink for long url {} by {}: {}",
+            shortlink.owner_email(),
+            shortlink.link_long(),
+            err
+        );
+        return LinkPostResponse::Exception {
+            reason: "Failed to store shortlink",
+            long,
+            workspace,
+        };
+    };
+
+    // fetch the title and favicon of the destination in the background
+    spawn_page_meta_fetch(state, shortlink.clone(), url);
+
+    let host = super::shared::default_host(state, host).await;
+    LinkPostResponse::Ok {
+        email: email.to_owned(),
+        long: shortlink.link_canonical().to_string(),
+        short: shortlink.link_short(super::shared::scheme_for_host(&host), &host),
+        back_path: back_path(workspace),
+    }
+}
+
+async fn post_delete(
+    state: &crate::router::State,
+    email_hash: &str,
+    params: PostParams,
+) -> LinkPostResponse {
+    let workspace = params.workspace;
+    let short = params.value;
+    if short.is_empty() {
+        return LinkPostResponse::ShortUrlMissing { workspace };
+    }
+    let link_domain = params.domain.filter(|domain| !domain.is_empty());
+    LinkPostResponse::Other(
+        match state
+            .storage
+            .delete_shortlink(&short, link_domain.as_deref(), email_hash)
+            .await
+        {
+            Ok(_) => crate::router::shared::InfoTemplate {
+                title: "Shortlink Deleted".to_string(),
+                message: format!("The shortlink '{}' has been deleted.", short),
+                back_path: back_path(workspace),
+            }
+            .into_response(),
+            Err(err) => crate::router::shared::ErrorTemplate {
+                title: "Failed to Delete Shortlink".to_string(),
+                message: format!(
+                    "The shortlink '{}' could not be deleted. {}. Please try again later.",
+                    short, err
+                ),
+                back_path: back_path(workspace),
+            }
+            .into_response(),
+        },
+    )
+}
+
+async fn post_page_meta(
+    state: &crate::router::State,
+    email_hash: &str,
+    params: PostParams,
+) -> LinkPostResponse {
+    let workspace = params.workspace;
+    let short = params.value;
+    if short.is_empty() {
+        return LinkPostResponse::ShortUrlMissing { workspace };
+    }
+    let meta = PageMeta {
+        title: non_empty(params.page_title),
+        favicon: non_empty(params.page_favicon),
+    };
+    let invalid = if meta
+        .title
+        .as_ref()
+        .map(|title| title.chars().count() > 256)
+        .unwrap_or_default()
+    {
+        Some("The page title can be at most 256 characters long.")
+    } else if meta
+        .favicon
+        .as_ref()
+        .map(|favicon| {
+            !reqwest::Url::parse(favicon)
+                .map(|url| url.scheme() == "https" || url.scheme() == "http")
+                .unwrap_or_default()
+        })
+        .unwrap_or_default()
+    {
+        Some("The favicon has to be an http(s) URL.")
+    } else {
+        None
+    };
+    if let Some(message) = invalid {
+        return LinkPostResponse::Other(
+            (
+                StatusCode::BAD_REQUEST,
+                crate::router::shared::ErrorTemplate {
+                    title: "Invalid Page Metadata".to_string(),
+                    message: message.to_string(),
+                    back_path: back_path(workspace),
+                },
+            )
+                .into_response(),
+        );
+    }
+    let link_domain = params.domain.filter(|domain| !domain.is_empty());
+    LinkPostResponse::Other(
+        match state
+            .storage
+            .override_shortlink_page_meta(&short, link_domain.as_deref(), email_hash, &meta)
+            .await
+        {
+            Ok(cleared) => {
+                // refetch the metadata of the destination once the override is cleared
+                if let Some(shortlink) = cleared {
+                    if let Ok(url) = reqwest::Url::parse(shortlink.link_canonical()) {
+                        spawn_page_meta_fetch(state, shortlink, url);
                     }
-                    LinkPostResponse::Other(match state.storage.delete_shortlink(&short, identity.email_hash()).await {
-                        Ok(_) => {
-                            crate::router::shared::InfoTemplate {
-                                title: "Shortlink Deleted".to_string(),
-                                message: format!("The shortlink '{}' has been deleted.", short),
-                                back_path: "/link".to_string(),
-                            }.into_response()
-                        }
-                        Err(err) => {
-                            crate::router::shared::ErrorTemplate {
-                                title: "Failed to Delete Shortlink".to_string(),
-                                message: format!("The shortlink '{}' could not be deleted. {}. Please try again later.", short, err),
-                                back_path: "/link".to_string(),
-                            }.into_response()
-                        }
-                    })
                 }
-                other => LinkPostResponse::BadAction(other.to_string()),
-            };
-        }
+                crate::router::shared::InfoTemplate {
+                    title: "Page Metadata Updated".to_string(),
+                    message: format!(
+                        "The page title and favicon of shortlink '{}' have been updated.",
+                        short
+                    ),
+                    back_path: back_path(workspace),
+                }
+                .into_response()
+            }
+            Err(err) => crate::router::shared::ErrorTemplate {
+                title: "Failed to Update Page Metadata".to_string(),
+                message: format!(
+                    "The page title and favicon of shortlink '{}' could not be updated. {}.",
+                    short, err
+                ),
+                back_path: back_path(workspace),
+            }
+            .into_response(),
+        },
+    )
+}
+
+async fn post_note(
+    state: &crate::router::State,
+    email_hash: &str,
+    params: PostParams,
+) -> LinkPostResponse {
+    let workspace = params.workspace;
+    let short = params.value;
+    if short.is_empty() {
+        return LinkPostResponse::ShortUrlMissing { workspace };
     }
-    LinkPostResponse::Forbidden
+    let title = non_empty(params.title);
+    let note = non_empty(params.note);
+    let invalid = if title
+        .as_ref()
+        .map(|title| title.chars().count() > 256)
+        .unwrap_or_default()
+    {
+        Some("The title can be at most 256 characters long.")
+    } else if note
+        .as_ref()
+        .map(|note| note.chars().count() > 4096)
+        .unwrap_or_default()
+    {
+        Some("The note can be at most 4096 characters long.")
+    } else {
+        None
+    };
+    if let Some(message) = invalid {
+        return LinkPostResponse::Other(
+            (
+                StatusCode::BAD_REQUEST,
+                crate::router::shared::ErrorTemplate {
+                    title: "Invalid Note".to_string(),
+                    message: message.to_string(),
+                    back_path: back_path(workspace),
+                },
+            )
+                .into_response(),
+        );
+    }
+    let link_domain = params.domain.filter(|domain| !domain.is_empty());
+    LinkPostResponse::Other(
+        match state
+            .storage
+            .set_shortlink_note(
+                &short,
+                link_domain.as_deref(),
+                email_hash,
+                title.as_deref(),
+                note.as_deref(),
+            )
+            .await
+        {
+            Ok(_) => crate::router::shared::InfoTemplate {
+                title: "Note Updated".to_string(),
+                message: format!(
+                    "The title and note of shortlink '{}' have been updated.",
+                    short
+                ),
+                back_path: back_path(workspace),
+            }
+            .into_response(),
+            Err(err) => crate::router::shared::ErrorTemplate {
+                title: "Failed to Update Note".to_string(),
+                message: format!(
+                    "The title and note of shortlink '{}' could not be updated. {}.",
+                    short, err
+                ),
+                back_path: back_path(workspace),
+            }
+            .into_response(),
+        },
+    )
+}
+
+async fn post_preview(
+    state: &crate::router::State,
+    email_hash: &str,
+    params: PostParams,
+) -> LinkPostResponse {
+    let workspace = params.workspace;
+    let short = params.value;
+    if short.is_empty() {
+        return LinkPostResponse::ShortUrlMissing { workspace };
+    }
+    let title = non_empty(params.preview_title);
+    let description = non_empty(params.preview_description);
+    let image = non_empty(params.preview_image);
+    let invalid = if title
+        .as_ref()
+        .map(|title| title.chars().count() > 256)
+        .unwrap_or_default()
+    {
+        Some("The preview title can be at most 256 characters long.")
+    } else if description
+        .as_ref()
+        .map(|description| description.chars().count() > 1024)
+        .unwrap_or_default()
+    {
+        Some("The preview description can be at most 1024 characters long.")
+    } else if image
+        .as_ref()
+        .map(|image| {
+            !reqwest::Url::parse(image)
+                .map(|url| url.scheme() == "https" || url.scheme() == "http")
+                .unwrap_or_default()
+        })
+        .unwrap_or_default()
+    {
+        Some("The preview image has to be an http(s) URL.")
+    } else {
+        None
+    };
+    if let Some(message) = invalid {
+        return LinkPostResponse::Other(
+            (
+                StatusCode::BAD_REQUEST,
+                crate::router::shared::ErrorTemplate {
+                    title: "Invalid Social Preview".to_string(),
+                    message: message.to_string(),
+                    back_path: back_path(workspace),
+                },
+            )
+                .into_response(),
+        );
+    }
+    let link_domain = params.domain.filter(|domain| !domain.is_empty());
+    LinkPostResponse::Other(
+        match state
+            .storage
+            .set_shortlink_preview(
+                &short,
+                link_domain.as_deref(),
+                email_hash,
+                title.as_deref(),
+                description.as_deref(),
+                image.as_deref(),
+            )
+            .await
+        {
+            Ok(_) => crate::router::shared::InfoTemplate {
+                title: "Social Preview Updated".to_string(),
+                message: format!(
+                    "The social preview of shortlink '{}' has been updated.",
+                    short
+                ),
+                back_path: back_path(workspace),
+            }
+            .into_response(),
+            Err(err) => crate::router::shared::ErrorTemplate {
+                title: "Failed to Update Social Preview".to_string(),
+                message: format!(
+                    "The social preview of shortlink '{}' could not be updated. {}.",
+                    short, err
+                ),
+                back_path: back_path(workspace),
+            }
+            .into_response(),
+        },
+    )
+}
+
+async fn post_transfer(
+    state: &crate::router::State,
+    email: &str,
+    email_hash: &str,
+    params: PostParams,
+) -> LinkPostResponse {
+    let short = params.value;
+    if short.is_empty() {
+        return LinkPostResponse::ShortUrlMissing {
+            workspace: params.workspace,
+        };
+    }
+    let recipient = params.recipient.unwrap_or_default().trim().to_lowercase();
+    let link_domain = params.domain.filter(|domain| !domain.is_empty());
+    let invalid = match state
+        .storage
+        .get_shortlink(link_domain.as_deref(), &short)
+        .await
+    {
+        Some(shortlink) => LinkTransfer::validate(&shortlink, email_hash, email, &recipient).err(),
+        None => Some("The shortlink does not exist."),
+    };
+    if let Some(message) = invalid {
+        return LinkPostResponse::Other(
+            crate::router::shared::ErrorTemplate {
+                title: "Invalid Transfer".to_string(),
+                message: message.to_string(),
+                back_path: "/link".to_string(),
+            }
+            .into_response(),
+        );
+    }
+    LinkPostResponse::Other(
+        match state
+            .storage
+            .add_link_transfer(
+                &short,
+                email_hash,
+                email,
+                &crate::services::hash_email(&recipient),
+                &recipient,
+            )
+            .await
+        {
+            Ok(_) => crate::router::shared::InfoTemplate {
+                title: "Shortlink Transfer Pending".to_string(),
+                message: format!(
+                    "The shortlink '{}' will be transferred once {} accepts it while logged in.",
+                    short, recipient
+                ),
+                back_path: "/link".to_string(),
+            }
+            .into_response(),
+            Err(err) => crate::router::shared::ErrorTemplate {
+                title: "Failed to Transfer Shortlink".to_string(),
+                message: format!(
+                    "The shortlink '{}' could not be transferred. {}.",
+                    short, err
+                ),
+                back_path: "/link".to_string(),
+            }
+            .into_response(),
+        },
+    )
+}
+
+async fn post_transfer_update(
+    state: &crate::router::State,
+    email_hash: &str,
+    params: PostParams,
+) -> LinkPostResponse {
+    let id: i32 = match params.value.parse() {
+        Ok(id) => id,
+        Err(_) => return LinkPostResponse::BadAction(params.action),
+    };
+    let accept = params.action == "transfer_accept";
+    let result = if accept {
+        state.storage.accept_link_transfer(id, email_hash).await
+    } else {
+        state.storage.cancel_link_transfer(id, email_hash).await
+    };
+    LinkPostResponse::Other(match result {
+        Ok(_) => crate::router::shared::InfoTemplate {
+            title: if accept {
+                "Shortlink Transfer Accepted"
+            } else {
+                "Shortlink Transfer Cancelled"
+            }
+            .to_string(),
+            message: if accept {
+                "The shortlink is now yours to manage.".to_string()
+            } else {
+                "The shortlink transfer has been cancelled.".to_string()
+            },
+            back_path: "/link".to_string(),
+        }
+        .into_response(),
+        Err(err) => crate::router::shared::ErrorTemplate {
+            title: "Failed to Update Shortlink Transfer".to_string(),
+            message: format!("The shortlink transfer could not be updated. {}.", err),
+            back_path: "/link".to_string(),
+        }
+        .into_response(),
+    })
 }
 
 enum LinkPostResponse {
     BadRequest {
-        reason: &'static str,
+        reason: String,
         long: String,
+        workspace: Option<i32>,
+    },
+    ShortUrlMissing {
+        workspace: Option<i32>,
     },
-    ShortUrlMissing,
     BadAction(String),
     Forbidden,
     Exception {
         reason: &'static str,
         long: String,
+        workspace: Option<i32>,
     },
     Ok {
         email: String,
         long: String,
         short: String,
-        hash: String,
+        back_path: String,
     },
     Other(Response),
 }
@@ -227,7 +970,11 @@ enum LinkPostResponse {
 impl IntoResponse for LinkPostResponse {
     fn into_response(self) -> Response {
         match self {
-            LinkPostResponse::BadRequest { reason, long } => (
+            LinkPostResponse::BadRequest {
+                reason,
+                long,
+                workspace,
+            } => (
                 StatusCode::BAD_REQUEST,
                 super::shared::ErrorTemplate {
                     title: "Invalid Long URL".to_string(),
@@ -236,16 +983,16 @@ impl IntoResponse for LinkPostResponse {
                     } else {
                         format!("The long URL '{}' is invalid. {}", long, reason)
                     },
-                    back_path: format!("/link?long={}", long),
+                    back_path: long_back_path(&long, workspace),
                 },
             )
                 .into_response(),
-            LinkPostResponse::ShortUrlMissing => (
+            LinkPostResponse::ShortUrlMissing { workspace } => (
                 StatusCode::BAD_REQUEST,
                 super::shared::ErrorTemplate {
                     title: "Short Url Missing".to_string(),
                     message: "Cannot delete shortlink without short url (hash).".to_string(),
-                    back_path: "/link".to_string(),
+                    back_path: back_path(workspace),
                 },
             )
                 .into_response(),
@@ -262,17 +1009,21 @@ impl IntoResponse for LinkPostResponse {
                 StatusCode::FORBIDDEN,
                 super::shared::ErrorTemplate {
                     title: "Forbidden".to_string(),
-                    message: "You are not authorized for creating shortlinks.".to_string(),
+                    message: "You are not authorized for managing these shortlinks.".to_string(),
                     back_path: "/".to_string(),
                 },
             )
                 .into_response(),
-            LinkPostResponse::Exception { reason, long } => (
+            LinkPostResponse::Exception {
+                reason,
+                long,
+                workspace,
+            } => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 super::shared::ErrorTemplate {
                     title: reason.to_string(),
                     message: format!("{} for '{}'. Please try again later.", reason, long),
-                    back_path: format!("/link?long={}", long),
+                    back_path: long_back_path(&long, workspace),
                 },
             )
                 .into_response(),
@@ -280,12 +1031,12 @@ impl IntoResponse for LinkPostResponse {
                 email,
                 long,
                 short,
-                hash,
+                back_path,
             } => PostOkTemplate {
                 email,
                 long,
                 short,
-                hash,
+                back_path,
             }
             .into_response(),
             LinkPostResponse::Other(response) => response,
@@ -0,0 +1,93 @@
+use std::{fmt::Write, sync::Arc};
+
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// Whether or not the request carries the configured bearer token.
+fn is_authorized(token: &str, headers: &HeaderMap) -> bool {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|given| orion::util::secure_cmp(given.trim().as_bytes(), token.as_bytes()).is_ok())
+        .unwrap_or_default()
+}
+
+/// Expose the internal counters of this service in the Prometheus text format,
+/// to scrapers authorized with the metrics token.
+pub async fn get(State(state): State<Arc<crate::router::State>>, headers: HeaderMap) -> Response {
+    match state.metrics_token.as_deref() {
+        None => return StatusCode::NOT_FOUND.into_response(),
+        Some(token) if !is_authorized(token, &headers) => {
+            return StatusCode::UNAUTHORIZED.into_response()
+        }
+        Some(_) => (),
+    }
+
+    let mut body = String::new();
+
+    let caches = [
+        (
+            "shortlink",
+            state.storage.shortlink_cache().hits(),
+            state.storage.shortlink_cache().misses(),
+            state.storage.shortlink_cache().size(),
+        ),
+        (
+            "domain",
+            state.storage.domain_cache().hits(),
+            state.storage.domain_cache().misses(),
+            state.storage.domain_cache().size(),
+        ),
+        (
+            "webhook",
+            state.storage.webhook_cache().hits(),
+            state.storage.webhook_cache().misses(),
+            state.storage.webhook_cache().size(),
+        ),
+    ];
+    let _ = writeln!(
+        body,
+        "# HELP bckt_cache_hits_total Cache hits since the process started."
+    );
+    let _ = writeln!(body, "# TYPE bckt_cache_hits_total counter");
+    for (name, hits, _, _) in caches {
+        let _ = writeln!(body, "bckt_cache_hits_total{{cache=\"{name}\"}} {hits}");
+    }
+    let _ = writeln!(
+        body,
+        "# HELP bckt_cache_misses_total Cache misses since the process started."
+    );
+    let _ = writeln!(body, "# TYPE bckt_cache_misses_total counter");
+    for (name, _, misses, _) in caches {
+        let _ = writeln!(body, "bckt_cache_misses_total{{cache=\"{name}\"}} {misses}");
+    }
+    let _ = writeln!(body, "# HELP bckt_cache_entries Entries currently cached.");
+    let _ = writeln!(body, "# TYPE bckt_cache_entries gauge");
+    for (name, _, _, size) in caches {
+        let _ = writeln!(body, "bckt_cache_entries{{cache=\"{name}\"}} {size}");
+    }
+
+    // counted in memory, such that they reset whenever the process restarts
+    let secrets = state.storage.secret_counters();
+    let _ = writeln!(
+        body,
+        "# HELP bckt_secrets_read_total Secrets revealed by this process since it started; resets on restart."
+    );
+    let _ = writeln!(body, "# TYPE bckt_secrets_read_total counter");
+    let _ = writeln!(body, "bckt_secrets_read_total {}", secrets.read());
+    let _ = writeln!(
+        body,
+        "# HELP bckt_secrets_purged_total Expired secrets purged by this process since it started; resets on restart."
+    );
+    let _ = writeln!(body, "# TYPE bckt_secrets_purged_total counter");
+    let _ = writeln!(body, "bckt_secrets_purged_total {}", secrets.purged());
+
+    let mut headers = HeaderMap::new();
+    headers.insert("content-type", "text/plain; version=0.0.4".parse().unwrap());
+
+    (headers, body).into_response()
+}
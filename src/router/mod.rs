@@ -1,6 +1,7 @@
 use std::{path::PathBuf, sync::Arc};
 
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -11,32 +12,171 @@ use tower_http::{
     trace::TraceLayer,
 };
 
+mod collection;
+mod digest;
+mod domain;
 mod index;
 mod link;
 mod login;
+// its query struct is not extracted by any route yet
+#[allow(dead_code)]
 mod logout;
 mod memory;
+mod metrics;
 mod not_found;
+mod rate_limit;
 mod redirect;
+mod secret;
 mod shared;
+mod webhook;
+mod workspace;
 
 #[derive(Debug, Clone)]
 pub struct State {
     pub auth: Arc<crate::services::Auth>,
     pub storage: crate::services::Storage,
+    pub page_fetcher: Arc<dyn crate::services::PageFetcher>,
+    pub shortener_resolver: Arc<dyn crate::services::ShortenerResolver>,
+    pub txt_resolver: Arc<dyn crate::services::TxtResolver>,
+    pub webhooks: crate::services::WebhookDispatcher,
+    pub blobs: Arc<dyn crate::services::BlobStore>,
+    pub digests: Arc<crate::services::Digests>,
+    pub mailer: crate::services::Mailer,
+    /// Hosts on which this service is publicly served, next to the custom domains.
+    pub public_hosts: Vec<String>,
+    /// Bearer token required to read the metrics, which are not served without one.
+    pub metrics_token: Option<String>,
+    /// Amount of proxies in front of the service which append to `X-Forwarded-For`.
+    pub trusted_proxy_hops: usize,
 }
 
 fn new_root(state: State) -> Router {
+    // every magic link request sends an email, so these are the most scarce
+    let login_limit = rate_limit::RateLimit::new(5, 1.0 / 60.0, state.trusted_proxy_hops);
+    let link_limit = rate_limit::RateLimit::new(20, 1.0 / 6.0, state.trusted_proxy_hops);
+    let redirect_limit = rate_limit::RateLimit::new(60, 5.0, state.trusted_proxy_hops);
+    let secret_limit = rate_limit::RateLimit::new(20, 1.0 / 6.0, state.trusted_proxy_hops);
+
     Router::new()
         .route("/", get(index::get))
         .route("/robots.txt", get(memory::get_robots_txt))
         .route("/sitemap.xml", get(memory::get_sitemap_xml))
+        .route("/metrics", get(metrics::get))
+        .route("/domain", get(domain::get))
+        .route("/domain", post(domain::post))
         .route("/link", get(link::get))
-        .route("/link", post(link::post))
+        .route(
+            "/link",
+            post(link::post).layer(middleware::from_fn_with_state(
+                link_limit,
+                rate_limit::limit,
+            )),
+        )
+        .route("/link/export", get(link::get_export))
+        .route("/link/digest", get(digest::get))
+        .route("/link/digest", post(digest::post))
+        .route("/link/webhook", get(webhook::get))
+        .route("/link/webhook", post(webhook::post))
+        .route("/link/collection", get(collection::get))
+        .route("/link/collection", post(collection::post))
         .route("/login", get(login::get))
-        .route("/login", post(login::post))
+        .route(
+            "/login",
+            post(login::post).layer(middleware::from_fn_with_state(
+                login_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
         .route("/logout", get(logout::get))
-        .route("/:hash", get(redirect::get))
+        .route("/secret", get(secret::get))
+        .route(
+            "/secret",
+            post(secret::post).layer(middleware::from_fn_with_state(
+                secret_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route("/secret/manage", get(secret::get_manage))
+        .route("/secret/manage", post(secret::post_manage))
+        .route(
+            "/secret/file",
+            post(secret::post_file).layer(middleware::from_fn_with_state(
+                secret_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/secret/request",
+            post(secret::post_request).layer(middleware::from_fn_with_state(
+                secret_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/r/:id",
+            get(secret::get_request).layer(middleware::from_fn_with_state(
+                redirect_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/r/:id",
+            post(secret::post_request_answer).layer(middleware::from_fn_with_state(
+                secret_limit,
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/s/:id",
+            get(secret::get_view).layer(middleware::from_fn_with_state(
+                redirect_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/s/:id/reveal",
+            post(secret::post_reveal).layer(middleware::from_fn_with_state(
+                redirect_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/s/:id/login",
+            get(secret::get_recipient_login).layer(middleware::from_fn_with_state(
+                redirect_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/s/:id/login",
+            post(secret::post_recipient_login).layer(middleware::from_fn_with_state(
+                login_limit,
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/s/:id/file",
+            get(secret::get_file).layer(middleware::from_fn_with_state(
+                redirect_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route("/workspace", get(workspace::get))
+        .route("/workspace", post(workspace::post))
+        .route(
+            "/:hash",
+            get(redirect::get).layer(middleware::from_fn_with_state(
+                redirect_limit.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/:hash/preview",
+            get(redirect::get_preview).layer(middleware::from_fn_with_state(
+                redirect_limit,
+                rate_limit::limit,
+            )),
+        )
         .with_state(Arc::new(state))
         .layer(CookieManagerLayer::new())
 }
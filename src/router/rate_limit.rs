@@ -0,0 +1,134 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use askama_axum::{IntoResponse, Response};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header::RETRY_AFTER, HeaderValue, Request, StatusCode},
+    middleware::Next,
+};
+
+use crate::{
+    router::shared::ErrorTemplate,
+    services::rate_limit::{client_ip, RateLimiter},
+};
+
+/// A rate limit budget for a single route.
+#[derive(Debug)]
+pub struct RateLimit {
+    limiter: RateLimiter,
+    trusted_proxy_hops: usize,
+}
+
+impl RateLimit {
+    pub fn new(burst: u32, per_second: f64, trusted_proxy_hops: usize) -> Arc<Self> {
+        Arc::new(Self {
+            limiter: RateLimiter::new(burst, per_second),
+            trusted_proxy_hops,
+        })
+    }
+}
+
+pub async fn limit<B>(
+    State(rate_limit): State<Arc<RateLimit>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    // the peer is unknown when the router is not served with connect info, and those
+    // clients are let through, as a shared budget would throttle all of them at once
+    let client = match client_ip(request.headers(), peer, rate_limit.trusted_proxy_hops) {
+        Some(client) => client,
+        None => {
+            tracing::debug!(
+                "Not rate limiting request to {}: the client address is unknown",
+                request.uri().path()
+            );
+            return next.run(request).await;
+        }
+    };
+    match rate_limit.limiter.check(client) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => too_many_requests(retry_after),
+    }
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        ErrorTemplate {
+            title: "429 — Too Many Requests".to_string(),
+            message: format!(
+                "You made too many requests, please try again in {} second(s).",
+                seconds
+            ),
+            back_path: "/".to_string(),
+        },
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(seconds));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn router(trusted_proxy_hops: usize) -> Router {
+        Router::new().route(
+            "/",
+            get(|| async { "ok" }).layer(middleware::from_fn_with_state(
+                RateLimit::new(1, 0.001, trusted_proxy_hops),
+                limit,
+            )),
+        )
+    }
+
+    fn request(forwarded_for: Option<&str>) -> Request<Body> {
+        let mut request = Request::builder().uri("/");
+        if let Some(forwarded_for) = forwarded_for {
+            request = request.header("x-forwarded-for", forwarded_for);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_limit_unknown_client() {
+        // served without connect info and without a forwarded address
+        for trusted_proxy_hops in [0, 1] {
+            let router = router(trusted_proxy_hops);
+            for _ in 0..3 {
+                let response = router.clone().oneshot(request(None)).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limit_forwarded_client() {
+        let router = router(1);
+        let response = router
+            .clone()
+            .oneshot(request(Some("1.1.1.1")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router
+            .clone()
+            .oneshot(request(Some("1.1.1.1")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(RETRY_AFTER));
+        let response = router.oneshot(request(Some("2.2.2.2"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -1,15 +1,148 @@
 use std::sync::Arc;
 
+use askama::Template;
 use askama_axum::{IntoResponse, Response};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Host, Path, State},
+    http::{
+        header::{REFERER, USER_AGENT},
+        HeaderMap, Method, StatusCode,
+    },
     response::Redirect,
 };
 
+use crate::{
+    data::{Domain, Shortlink},
+    services::{
+        idn,
+        user_agent::Visitor,
+        webhook::{ClickEvent, WebhookJob},
+    },
+};
+
+#[derive(Template)]
+#[template(path = "../templates/content/preview.html")]
+pub struct PreviewTemplate {
+    pub shortlink: Shortlink,
+    pub short: String,
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/link_preview.html")]
+pub struct LinkPreviewTemplate {
+    pub shortlink: Shortlink,
+    pub short: String,
+    pub display: String,
+    pub warning: Option<String>,
+}
+
+/// Resolve the hash within the namespace of the requested custom domain, if any.
+async fn resolve(state: &crate::router::State, host: &str, hash: &str) -> Option<Shortlink> {
+    let domain = state
+        .storage
+        .get_domain(&Domain::normalize_host(host))
+        .await;
+    let domain = domain.as_ref().map(|domain| domain.host());
+    state.storage.get_shortlink(domain, hash).await
+}
+
+/// Notify the webhooks registered for the shortlink of a click, in the background.
+fn spawn_click_webhooks(state: &crate::router::State, shortlink: &Shortlink, host: &str) {
+    let storage = state.storage.clone();
+    let webhooks = state.webhooks.clone();
+    let event = ClickEvent::new(
+        shortlink.link_short(super::shared::scheme_for_host(host), host),
+        shortlink.link_hash().to_owned(),
+        shortlink.domain().map(str::to_owned),
+        shortlink.link_canonical().to_owned(),
+    );
+    let shortlink = shortlink.clone();
+    tokio::spawn(async move {
+        for webhook in storage.get_webhooks_for_shortlink(&shortlink).await {
+            webhooks.enqueue(WebhookJob {
+                webhook_id: webhook.id(),
+                url: webhook.url().to_owned(),
+                secret: webhook.secret().to_owned(),
+                event: event.clone(),
+            });
+        }
+    });
+}
+
+/// Count a visit of the shortlink in the background, such that redirects never wait for it.
+fn spawn_click_count(
+    state: &crate::router::State,
+    shortlink: &Shortlink,
+    visitor: Visitor,
+    referrer_host: Option<String>,
+) {
+    let storage = state.storage.clone();
+    let shortlink = shortlink.clone();
+    tokio::spawn(async move {
+        if let Err(err) = storage
+            .add_shortlink_click(&shortlink, visitor, referrer_host.as_deref())
+            .await
+        {
+            tracing::error!(
+                "Failed to count click for shortlink {}: {}",
+                shortlink.link_hash(),
+                err
+            );
+        }
+    });
+}
+
+/// The host of the page which linked to the shortlink, unless it is the shortlink host itself.
+fn referrer_host(headers: &HeaderMap, host: &str) -> Option<String> {
+    headers
+        .get(REFERER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|referrer| reqwest::Url::parse(referrer).ok())
+        .and_then(|url| url.host_str().map(|host| host.to_lowercase()))
+        .filter(|referrer| referrer.len() <= 253 && *referrer != Domain::normalize_host(host))
+}
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, crate::router::shared::ErrorTemplate {
+        title: "Not Found".to_string(),
+        message: "The requested shortlink does not exist. It might have been deleted or perhaps it never existed to begin with. Please try with another one.".to_string(),
+        back_path: "/".to_string(),
+    }).into_response()
+}
+
+/// Show where a shortlink goes to without following it,
+/// warning about destinations which might impersonate another domain.
+pub async fn get_preview(
+    State(state): State<Arc<crate::router::State>>,
+    Host(host): Host,
+    Path(hash): Path<String>,
+) -> Response {
+    match resolve(&state, &host, &hash).await {
+        Some(shortlink) => {
+            let short = shortlink.link_short(super::shared::scheme_for_host(&host), &host);
+            let warning = reqwest::Url::parse(shortlink.link_canonical())
+                .ok()
+                .and_then(|url| url.host_str().map(idn::homograph_warning))
+                .flatten()
+                .map(|warning| warning.to_string());
+            LinkPreviewTemplate {
+                display: idn::display_url(shortlink.link_canonical()),
+                shortlink,
+                short,
+                warning,
+            }
+            .into_response()
+        }
+        None => not_found(),
+    }
+}
+
 pub async fn get(
     State(state): State<Arc<crate::router::State>>,
+    Host(host): Host,
     Path(hash): Path<String>,
+    method: Method,
+    headers: HeaderMap,
 ) -> Response {
     match hash.as_str() {
         "code" => Redirect::permanent("https://github.com/plabayo/bucket").into_response(),
@@ -18,15 +151,32 @@ pub async fn get(
             "https://upload.wikimedia.org/wikipedia/commons/3/3b/Sand_bucket.jpg",
         )
         .into_response(),
+        slug if slug.starts_with('@') => {
+            super::collection::get_public(&state, &host, &slug[1..]).await
+        }
         hash => {
-            if let Some(link) = state.storage.get_shortlink(hash).await {
-                Redirect::temporary(link.link_long()).into_response()
+            if let Some(link) = resolve(&state, &host, hash).await {
+                let visitor = crate::services::user_agent::classify(&method, &headers);
+                if visitor == Visitor::Human {
+                    spawn_click_webhooks(&state, &link, &host);
+                }
+                spawn_click_count(&state, &link, visitor, referrer_host(&headers, &host));
+                // unfurl bots get the custom preview of the owner instead of the redirect
+                let user_agent = headers
+                    .get(USER_AGENT)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default();
+                if link.has_preview() && crate::services::user_agent::is_unfurl_bot(user_agent) {
+                    let short = link.link_short(super::shared::scheme_for_host(&host), &host);
+                    return PreviewTemplate {
+                        shortlink: link,
+                        short,
+                    }
+                    .into_response();
+                }
+                Redirect::temporary(link.link_canonical()).into_response()
             } else {
-                (StatusCode::NOT_FOUND, crate::router::shared::ErrorTemplate {
-                    title: "Not Found".to_string(),
-                    message: "The requested shortlink does not exist. It might have been deleted or perhaps it never existed to begin with. Please try with another one.".to_string(),
-                    back_path: "/".to_string(),
-                }).into_response()
+                not_found()
             }
         }
     }
@@ -0,0 +1,1026 @@
+use std::{io, sync::Arc};
+
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
+use axum::{
+    body::StreamBody,
+    extract::{BodyStream, Host, Path, Query, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
+    response::Redirect,
+    Form,
+};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use serde::Deserialize;
+use tower_cookies::{cookie::time::OffsetDateTime, Cookie, Cookies};
+
+use crate::data::{Secret, SecretRequest, SecretReveal, MAX_FILE_CIPHERTEXT_LEN, MAX_FILE_SIZE};
+
+/// Header containing the ticket to download the file of a revealed secret.
+const FILE_TICKET_HEADER: &str = "x-bckt-file-ticket";
+
+#[derive(Template)]
+#[template(path = "../templates/content/secret.html")]
+pub struct GetTemplate {
+    pub email: String,
+    pub max_file_size: u64,
+    /// The secrets requested by this user, most recent first.
+    pub requests: Vec<SecretRequest>,
+}
+
+pub async fn get(State(state): State<Arc<crate::router::State>>, cookies: Cookies) -> Response {
+    if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
+        if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
+            return GetTemplate {
+                email: identity.email().to_owned(),
+                max_file_size: MAX_FILE_SIZE,
+                requests: state
+                    .storage
+                    .get_secret_requests_for_owner(identity.email_hash())
+                    .await,
+            }
+            .into_response();
+        }
+    }
+    Redirect::temporary("/").into_response()
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/secret_ok.html")]
+pub struct PostOkTemplate {
+    /// The url of the secret, without the key, which is only known by the browser.
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+pub struct PostParams {
+    ciphertext: String,
+    burn_after_reading: Option<String>,
+    /// After how many views a secret which burns after reading is destroyed.
+    max_views: Option<i32>,
+    /// How long the secret can be read, in hours.
+    ttl: i64,
+    /// The salt used by the browser to stretch the passphrase, if any.
+    passphrase_salt: Option<String>,
+    passphrase_verifier: Option<String>,
+    /// The key of the encrypted file uploaded for this secret, if any.
+    file_key: Option<String>,
+    /// Whether to send an email to the creator once the secret is read.
+    read_receipt: Option<String>,
+    /// The email address of the only recipient allowed to read the secret, if any.
+    recipient: Option<String>,
+}
+
+pub async fn post(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Host(host): Host,
+    Form(params): Form<PostParams>,
+) -> Response {
+    let identity = match cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+    {
+        Some(identity) => identity,
+        None => {
+            return error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "You are not authorized for sharing secrets.".to_string(),
+            )
+        }
+    };
+
+    if !Secret::is_valid_ciphertext(&params.ciphertext) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Invalid Secret",
+            "The secret is either empty, too large or was not encrypted by your browser."
+                .to_string(),
+        );
+    }
+
+    if !Secret::is_valid_ttl(params.ttl) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Invalid Expiry",
+            "A secret has to expire within 1 hour and 30 days.".to_string(),
+        );
+    }
+
+    let max_views = params.max_views.unwrap_or(1);
+    if !Secret::is_valid_max_views(max_views) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Invalid Views",
+            "A secret can be viewed at least once and at most 100 times.".to_string(),
+        );
+    }
+
+    let mut secret = Secret::new(
+        params.ciphertext,
+        identity.email_hash().to_owned(),
+        params.burn_after_reading.is_some(),
+        params.ttl,
+    );
+    // the passphrase itself never leaves the browser
+    match (
+        params.passphrase_salt.filter(|salt| !salt.is_empty()),
+        params
+            .passphrase_verifier
+            .filter(|verifier| !verifier.is_empty()),
+    ) {
+        (Some(salt), Some(verifier))
+            if Secret::is_valid_encoding(&salt, 16) && Secret::is_valid_encoding(&verifier, 32) =>
+        {
+            secret = secret.with_passphrase(salt, &verifier);
+        }
+        (None, None) => (),
+        _ => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "Invalid Passphrase",
+                "The passphrase of the secret was not derived by your browser.".to_string(),
+            )
+        }
+    }
+    if params.burn_after_reading.is_some() {
+        secret = secret.with_max_views(max_views);
+    }
+    if let Some(recipient) = params
+        .recipient
+        .map(|recipient| recipient.trim().to_owned())
+        .filter(|recipient| !recipient.is_empty())
+    {
+        if !recipient.contains('@') || recipient.len() > 320 {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "Invalid Recipient",
+                format!("The recipient '{recipient}' is not a valid email address."),
+            );
+        }
+        secret = secret.with_recipient(crate::services::hash_email(&recipient));
+    }
+    if params.read_receipt.is_some() {
+        secret = secret.with_read_receipt(identity.email().to_owned());
+    }
+    if let Some(file_key) = params.file_key.filter(|key| !key.is_empty()) {
+        match state
+            .storage
+            .get_unattached_blob_size(&file_key, identity.email_hash())
+            .await
+        {
+            Some(size) => secret = secret.with_file(file_key, size),
+            None => {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "File Not Found",
+                    "The file of the secret was not uploaded, or is already shared.".to_string(),
+                )
+            }
+        }
+    }
+    match state.storage.add_secret(&secret).await {
+        Ok(()) => PostOkTemplate {
+            url: format!(
+                "{}://{}/s/{}",
+                super::shared::scheme_for_host(&host),
+                host,
+                secret.id()
+            ),
+        }
+        .into_response(),
+        Err(err) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to Store Secret",
+            format!("Your secret could not be stored: {err}."),
+        ),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/secret_manage.html")]
+pub struct ManageTemplate {
+    pub email: String,
+    /// The secrets shared by this user, most recent first, of which only metadata is known.
+    pub secrets: Vec<Secret>,
+}
+
+pub async fn get_manage(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+) -> Response {
+    if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
+        if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
+            return ManageTemplate {
+                email: identity.email().to_owned(),
+                secrets: state
+                    .storage
+                    .get_secrets_for_owner(identity.email_hash())
+                    .await,
+            }
+            .into_response();
+        }
+    }
+    Redirect::temporary("/").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct ManageParams {
+    action: String,
+    id: String,
+    /// By how many hours to extend the expiry of the secret.
+    hours: Option<i32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ManageAction {
+    Revoke,
+    /// Extend the expiry by the given amount of hours.
+    Extend(i32),
+}
+
+/// Parse the action to take on a secret, or the title and message of why it is invalid.
+fn manage_action(action: &str, hours: Option<i32>) -> Result<ManageAction, (&'static str, String)> {
+    match (action, hours) {
+        ("revoke", _) => Ok(ManageAction::Revoke),
+        ("extend", Some(hours)) if Secret::is_valid_ttl(hours.into()) => {
+            Ok(ManageAction::Extend(hours))
+        }
+        ("extend", _) => Err((
+            "Invalid Expiry",
+            "A secret can be extended by 1 hour up to 30 days.".to_string(),
+        )),
+        (other, _) => Err(("Bad Action", format!("Invalid action {other}."))),
+    }
+}
+
+/// Revoke a secret, or extend its expiry, which is only possible for its creator.
+pub async fn post_manage(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Form(params): Form<ManageParams>,
+) -> Response {
+    let identity = match cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+    {
+        Some(identity) => identity,
+        None => {
+            return manage_error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "You are not authorized for managing secrets.".to_string(),
+            )
+        }
+    };
+
+    let action = match manage_action(&params.action, params.hours) {
+        Ok(action) => action,
+        Err((title, message)) => return manage_error(StatusCode::BAD_REQUEST, title, message),
+    };
+    let result = match action {
+        ManageAction::Revoke => {
+            match state
+                .storage
+                .revoke_secret(&params.id, identity.email_hash())
+                .await
+            {
+                Ok(Some(key)) => {
+                    // the purge task retries deleting the file in case this fails
+                    if let Err(err) = state.blobs.delete(&key).await {
+                        tracing::error!("Failed to delete blob {}: {}", key, err);
+                    } else if let Err(err) = state.storage.delete_blob(&key).await {
+                        tracing::error!("Failed to forget blob {}: {}", key, err);
+                    }
+                    Ok(())
+                }
+                Ok(None) => Ok(()),
+                Err(err) => Err(err),
+            }
+        }
+        ManageAction::Extend(hours) => {
+            state
+                .storage
+                .extend_secret(&params.id, identity.email_hash(), hours)
+                .await
+        }
+    };
+
+    match result {
+        Ok(()) => Redirect::to("/secret/manage").into_response(),
+        Err(err) => manage_error(
+            StatusCode::BAD_REQUEST,
+            "Failed to Update Secret",
+            format!("The secret could not be updated: {err}."),
+        ),
+    }
+}
+
+/// Upload the encrypted file of a secret, which is streamed into the blob store,
+/// returning the key to create the secret with.
+pub async fn post_file(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    body: BodyStream,
+) -> Response {
+    let identity = match cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+    {
+        Some(identity) => identity,
+        None => return (StatusCode::FORBIDDEN, "not authorized").into_response(),
+    };
+
+    // the size is checked upfront, such that too large files are never stored
+    let len = match headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+    {
+        Some(len) if len > 28 && len <= MAX_FILE_CIPHERTEXT_LEN => len,
+        Some(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "file too large").into_response(),
+        None => return (StatusCode::LENGTH_REQUIRED, "length required").into_response(),
+    };
+
+    let key = nanoid::nanoid!(32);
+    if let Err(err) = state
+        .storage
+        .add_blob(&key, identity.email_hash(), len as i64)
+        .await
+    {
+        tracing::error!("Failed to add blob: {}", err);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    let body = body.map_err(io::Error::other);
+    if let Err(err) = state.blobs.put(&key, len, Box::pin(body)).await {
+        tracing::error!("Failed to store blob {}: {}", key, err);
+        return (StatusCode::BAD_REQUEST, "file could not be stored").into_response();
+    }
+    ([(CONTENT_TYPE, "text/plain")], key).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct FileParams {
+    ticket: String,
+}
+
+/// Download the encrypted file of a secret, using the ticket received when revealing it.
+pub async fn get_file(
+    State(state): State<Arc<crate::router::State>>,
+    Path(id): Path<String>,
+    Query(params): Query<FileParams>,
+) -> Response {
+    let key = match state.storage.use_file_ticket(&id, &params.ticket).await {
+        Some(key) => key,
+        None => return StatusCode::GONE.into_response(),
+    };
+    match state.blobs.get(&key).await {
+        Ok(Some(blob)) => (
+            [
+                (CONTENT_TYPE, "application/octet-stream"),
+                (CACHE_CONTROL, "no-store"),
+            ],
+            StreamBody::new(blob),
+        )
+            .into_response(),
+        Ok(None) => StatusCode::GONE.into_response(),
+        Err(err) => {
+            tracing::error!("Failed to get blob {}: {}", key, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/secret_view.html")]
+pub struct ViewTemplate {
+    pub id: String,
+    pub created_at: String,
+    pub expires_at: String,
+    pub burn_after_reading: bool,
+    /// How many more times the secret can be viewed, if it burns after reading.
+    pub views_left: Option<i32>,
+    pub passphrase_salt: Option<String>,
+    pub attempts_left: Option<i32>,
+    pub file_size: Option<String>,
+}
+
+/// Show the viewer of a secret, which only fetches and decrypts it within the browser
+/// once the visitor asks to reveal it, such that link previews cannot burn it.
+pub async fn get_view(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Path(id): Path<String>,
+) -> Response {
+    let visitor = visitor_email_hash(&state, &cookies, &id);
+    match state.storage.get_secret(&id).await {
+        Some(secret) if secret.is_expired() => expired(),
+        Some(secret) if secret.is_burned() => {
+            already_viewed(secret.max_views(), secret.viewed_at())
+        }
+        Some(secret) if !secret.is_readable_by(visitor.as_deref()) => RecipientTemplate {
+            id: secret.id().to_owned(),
+        }
+        .into_response(),
+        Some(secret) => ViewTemplate {
+            id: secret.id().to_owned(),
+            created_at: format_time(secret.created_at()),
+            expires_at: format_time(secret.expires_at()),
+            burn_after_reading: secret.burn_after_reading(),
+            views_left: secret.views_left(),
+            passphrase_salt: secret.passphrase_salt().map(str::to_owned),
+            attempts_left: secret.attempts_left(),
+            file_size: secret.file_size().map(format_size),
+        }
+        .into_response(),
+        None => not_found(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RevealParams {
+    /// Proof of the passphrase, derived from it by the browser.
+    verifier: Option<String>,
+}
+
+/// Reveal the ciphertext of a secret, as it was encrypted by the browser of its creator,
+/// burning it in case it was viewed as often as its creator allowed.
+///
+/// A wrong passphrase is answered with a `403`, with the attempts left as its body,
+/// and a visitor who is not logged in as the recipient of the secret with a `401`.
+pub async fn post_reveal(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Path(id): Path<String>,
+    Host(host): Host,
+    headers: HeaderMap,
+    Form(params): Form<RevealParams>,
+) -> Response {
+    let viewer = crate::services::user_agent::describe(&headers);
+    let visitor = visitor_email_hash(&state, &cookies, &id);
+    let reveal = match state
+        .storage
+        .reveal_secret(&id, params.verifier.as_deref(), &viewer, visitor.as_deref())
+        .await
+    {
+        Ok(reveal) => reveal,
+        Err(err) => {
+            tracing::error!("Failed to reveal secret {}: {}", id, err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if let SecretReveal::Revealed {
+        receipt_email: Some(email),
+        ..
+    } = &reveal
+    {
+        crate::services::secret::spawn_read_receipt(
+            state.mailer.clone(),
+            email.clone(),
+            format!(
+                "{}://{}/s/{}",
+                super::shared::scheme_for_host(&host),
+                host,
+                id
+            ),
+            viewer,
+        );
+    }
+    let exists = match reveal {
+        SecretReveal::Gone => state.storage.get_secret(&id).await.is_some(),
+        _ => true,
+    };
+    reveal_response(reveal, exists)
+}
+
+/// Answer an attempt to reveal a secret, where a secret which is gone
+/// is told apart by whether it still exists.
+fn reveal_response(reveal: SecretReveal, exists: bool) -> Response {
+    match reveal {
+        SecretReveal::Revealed {
+            ciphertext,
+            file_ticket,
+            ..
+        } => {
+            let mut response = (
+                [(CONTENT_TYPE, "text/plain"), (CACHE_CONTROL, "no-store")],
+                ciphertext,
+            )
+                .into_response();
+            if let Some(ticket) = file_ticket.and_then(|ticket| ticket.parse().ok()) {
+                response.headers_mut().insert(FILE_TICKET_HEADER, ticket);
+            }
+            response
+        }
+        SecretReveal::WrongPassphrase { attempts_left } => {
+            (StatusCode::FORBIDDEN, attempts_left.to_string()).into_response()
+        }
+        SecretReveal::NotRecipient => StatusCode::UNAUTHORIZED.into_response(),
+        SecretReveal::Gone if exists => StatusCode::GONE.into_response(),
+        SecretReveal::Gone => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/secret_recipient.html")]
+pub struct RecipientTemplate {
+    pub id: String,
+}
+
+#[derive(Deserialize)]
+pub struct RecipientLoginParams {
+    email: String,
+}
+
+/// Send a login link to the recipient of a secret, which can only be used to read that secret,
+/// and is therefore not limited to the authorized emails.
+///
+/// The response is the same for any address, such that it cannot be used to guess the recipient,
+/// and login links are only sent again after a cooldown.
+pub async fn post_recipient_login(
+    State(state): State<Arc<crate::router::State>>,
+    Host(host): Host,
+    Path(id): Path<String>,
+    Form(params): Form<RecipientLoginParams>,
+) -> Response {
+    let email = params.email.trim().to_lowercase();
+    if email.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            super::shared::ErrorTemplate {
+                title: "Email Required".to_string(),
+                message: "Please enter your email address.".to_string(),
+                back_path: format!("/s/{id}"),
+            },
+        )
+            .into_response();
+    }
+
+    let hash = crate::services::hash_email(&email);
+    match state.storage.get_secret(&id).await {
+        Some(secret)
+            if secret.is_active() && secret.recipient_email_hash() == Some(hash.as_str()) =>
+        {
+            match state.storage.claim_recipient_login(&id).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    tracing::debug!("Recipient login of secret {} is cooling down", id);
+                    return check_email(&email, &id);
+                }
+                Err(err) => {
+                    tracing::error!("Failed to claim recipient login of {}: {}", id, err);
+                    return check_email(&email, &id);
+                }
+            }
+            match state.auth.create_scoped_magic(&email, &id) {
+                Ok(magic) => crate::services::secret::spawn_recipient_login(
+                    state.mailer.clone(),
+                    email.clone(),
+                    format!(
+                        "{}://{}/s/{}/login?magic={}",
+                        super::shared::scheme_for_host(&host),
+                        host,
+                        id,
+                        magic
+                    ),
+                ),
+                Err(err) => tracing::error!("Failed to create recipient magic: {}", err),
+            }
+        }
+        Some(_) => (),
+        None => return not_found(),
+    }
+
+    check_email(&email, &id)
+}
+
+fn check_email(email: &str, id: &str) -> Response {
+    super::shared::InfoTemplate {
+        title: "Check Your Email".to_string(),
+        message: format!(
+            "If {email} is the recipient of this secret, a login link was sent to it. \
+            Open it in this browser to read the secret."
+        ),
+        back_path: format!("/s/{id}"),
+    }
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct RecipientMagicParams {
+    magic: String,
+}
+
+/// Log in the recipient of a secret using the link sent to them,
+/// with a cookie which is only sent along for that secret.
+pub async fn get_recipient_login(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Path(id): Path<String>,
+    Query(params): Query<RecipientMagicParams>,
+) -> Redirect {
+    if let Some((magic, expires_at)) = state.auth.verify_scoped_magic(params.magic, &id) {
+        let mut cookie = Cookie::new(crate::services::RECIPIENT_COOKIE_NAME, magic);
+        cookie.set_path(format!("/s/{id}"));
+        let offset = OffsetDateTime::from_unix_timestamp(expires_at as i64).unwrap();
+        cookie.set_expires(offset);
+        cookies.add(cookie);
+        tracing::debug!("login recipient of secret with magic link");
+    }
+    Redirect::to(&format!("/s/{id}"))
+}
+
+/// The hash of the email address as which the visitor is logged in, if any,
+/// either as the recipient of this secret only, or using a regular login.
+fn visitor_email_hash(state: &crate::router::State, cookies: &Cookies, id: &str) -> Option<String> {
+    cookies
+        .get(crate::services::RECIPIENT_COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_scoped_cookie(cookie.value(), id))
+        .or_else(|| {
+            cookies
+                .get(crate::services::COOKIE_NAME)
+                .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+        })
+        .map(|identity| identity.email_hash().to_owned())
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/secret_request_ok.html")]
+pub struct PostRequestOkTemplate {
+    pub id: String,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+pub struct PostRequestParams {
+    label: String,
+    /// The public key generated by the browser, of which it keeps the private key.
+    public_key: String,
+    /// How long the request can be answered, in hours.
+    ttl: i64,
+}
+
+/// Request a secret from someone else, who does not need to be logged in to send it.
+pub async fn post_request(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Host(host): Host,
+    Form(params): Form<PostRequestParams>,
+) -> Response {
+    let identity = match cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+    {
+        Some(identity) => identity,
+        None => {
+            return error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "You are not authorized for requesting secrets.".to_string(),
+            )
+        }
+    };
+
+    if !SecretRequest::is_valid_label(&params.label) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Invalid Request",
+            "Describe what you would like to receive, in at most 200 characters.".to_string(),
+        );
+    }
+    if !SecretRequest::is_valid_public_key(&params.public_key) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Invalid Request",
+            "The key of the request was not generated by your browser.".to_string(),
+        );
+    }
+    if !Secret::is_valid_ttl(params.ttl) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Invalid Expiry",
+            "A request has to expire within 1 hour and 30 days.".to_string(),
+        );
+    }
+
+    let request = SecretRequest::new(
+        identity.email_hash().to_owned(),
+        params.label.trim().to_owned(),
+        params.public_key,
+        params.ttl,
+    );
+    match state.storage.add_secret_request(&request).await {
+        Ok(()) => PostRequestOkTemplate {
+            id: request.id().to_owned(),
+            url: format!(
+                "{}://{}/r/{}",
+                super::shared::scheme_for_host(&host),
+                host,
+                request.id()
+            ),
+        }
+        .into_response(),
+        Err(err) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to Store Request",
+            format!("Your request could not be stored: {err}."),
+        ),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/secret_request.html")]
+pub struct RequestTemplate {
+    pub id: String,
+    pub label: String,
+    pub public_key: String,
+    pub expires_at: String,
+}
+
+#[derive(Template)]
+#[template(path = "../templates/content/secret_request_open.html")]
+pub struct RequestOpenTemplate {
+    pub request: SecretRequest,
+}
+
+/// Show a request for a secret, which can be answered by anyone with its link,
+/// and opened by its creator once it is.
+pub async fn get_request(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Path(id): Path<String>,
+) -> Response {
+    let request = match state.storage.get_secret_request(&id).await {
+        Some(request) => request,
+        None => return request_not_found(),
+    };
+    let is_owner = cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+        .map(|identity| identity.email_hash() == request.owner_email())
+        .unwrap_or_default();
+
+    if is_owner {
+        RequestOpenTemplate { request }.into_response()
+    } else if request.is_expired() {
+        expired()
+    } else if request.is_answered() {
+        already_answered()
+    } else {
+        RequestTemplate {
+            id: request.id().to_owned(),
+            label: request.label().to_owned(),
+            public_key: request.public_key().to_owned(),
+            expires_at: format_time(request.expires_at()),
+        }
+        .into_response()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AnswerParams {
+    ciphertext: String,
+    /// The public key of the ephemeral key pair used to encrypt the answer.
+    ephemeral_key: String,
+}
+
+/// Answer a request for a secret, with a secret encrypted to the public key of its creator.
+pub async fn post_request_answer(
+    State(state): State<Arc<crate::router::State>>,
+    Path(id): Path<String>,
+    Form(params): Form<AnswerParams>,
+) -> Response {
+    if !Secret::is_valid_ciphertext(&params.ciphertext)
+        || !SecretRequest::is_valid_public_key(&params.ephemeral_key)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            super::shared::ErrorTemplate {
+                title: "Invalid Secret".to_string(),
+                message:
+                    "The secret is either empty, too large or was not encrypted by your browser."
+                        .to_string(),
+                back_path: format!("/r/{id}"),
+            },
+        )
+            .into_response();
+    }
+
+    match state
+        .storage
+        .answer_secret_request(&id, &params.ciphertext, &params.ephemeral_key)
+        .await
+    {
+        Ok(true) => super::shared::InfoTemplate {
+            title: "Secret Sent".to_string(),
+            message: "Your secret was encrypted by your browser and can only be read by whoever \
+                requested it."
+                .to_string(),
+            back_path: "/".to_string(),
+        }
+        .into_response(),
+        Ok(false) => match state.storage.get_secret_request(&id).await {
+            Some(_) => already_answered(),
+            None => request_not_found(),
+        },
+        Err(err) => {
+            tracing::error!("Failed to answer secret request {}: {}", id, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                super::shared::ErrorTemplate {
+                    title: "Failed to Send Secret".to_string(),
+                    message: "Your secret could not be stored, please try again.".to_string(),
+                    back_path: format!("/r/{id}"),
+                },
+            )
+                .into_response()
+        }
+    }
+}
+
+fn already_answered() -> Response {
+    (
+        StatusCode::GONE,
+        super::shared::ErrorTemplate {
+            title: "Secret Already Sent".to_string(),
+            message: "This request was already answered, and cannot be answered again.".to_string(),
+            back_path: "/".to_string(),
+        },
+    )
+        .into_response()
+}
+
+fn request_not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        super::shared::ErrorTemplate {
+            title: "Request Not Found".to_string(),
+            message: "This request for a secret does not exist (anymore).".to_string(),
+            back_path: "/".to_string(),
+        },
+    )
+        .into_response()
+}
+
+fn already_viewed(max_views: Option<i32>, viewed_at: Option<DateTime<Utc>>) -> Response {
+    let times = match max_views {
+        Some(1) | None => "once".to_string(),
+        Some(max_views) => format!("{max_views} times"),
+    };
+    let message = match viewed_at {
+        Some(viewed_at) => format!(
+            "This secret could only be read {times}, and was last viewed on {}.",
+            format_time(viewed_at)
+        ),
+        None => format!("This secret could only be read {times}, and was already viewed."),
+    };
+    (
+        StatusCode::GONE,
+        super::shared::ErrorTemplate {
+            title: "Secret Already Viewed".to_string(),
+            message,
+            back_path: "/".to_string(),
+        },
+    )
+        .into_response()
+}
+
+fn expired() -> Response {
+    (
+        StatusCode::GONE,
+        super::shared::ErrorTemplate {
+            title: "Secret Expired".to_string(),
+            message: "This secret has expired, and can no longer be read.".to_string(),
+            back_path: "/".to_string(),
+        },
+    )
+        .into_response()
+}
+
+fn not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        super::shared::ErrorTemplate {
+            title: "Secret Not Found".to_string(),
+            message: "This secret does not exist (anymore).".to_string(),
+            back_path: "/".to_string(),
+        },
+    )
+        .into_response()
+}
+
+/// Format a size in bytes for humans.
+fn format_size(size: i64) -> String {
+    match size {
+        size if size >= 1024 * 1024 => format!("{:.1} MiB", size as f64 / (1024.0 * 1024.0)),
+        size if size >= 1024 => format!("{:.1} KiB", size as f64 / 1024.0),
+        size => format!("{size} bytes"),
+    }
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+fn manage_error(status: StatusCode, title: &str, message: String) -> Response {
+    (
+        status,
+        super::shared::ErrorTemplate {
+            title: title.to_string(),
+            message,
+            back_path: "/secret/manage".to_string(),
+        },
+    )
+        .into_response()
+}
+
+fn error(status: StatusCode, title: &str, message: String) -> Response {
+    (
+        status,
+        super::shared::ErrorTemplate {
+            title: title.to_string(),
+            message,
+            back_path: "/secret".to_string(),
+        },
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    async fn body(response: Response) -> String {
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_already_viewed() {
+        let viewed_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap();
+        let response = already_viewed(Some(3), Some(viewed_at));
+        assert_eq!(response.status(), StatusCode::GONE);
+        assert!(body(response)
+            .await
+            .contains("could only be read 3 times, and was last viewed on 2024-05-01 12:30 UTC."));
+
+        let response = already_viewed(None, None);
+        assert_eq!(response.status(), StatusCode::GONE);
+        assert!(body(response)
+            .await
+            .contains("could only be read once, and was already viewed."));
+    }
+
+    #[test]
+    fn test_manage_action() {
+        assert_eq!(manage_action("revoke", None).unwrap(), ManageAction::Revoke);
+        assert_eq!(
+            manage_action("extend", Some(24)).unwrap(),
+            ManageAction::Extend(24)
+        );
+        for hours in [None, Some(0), Some(-24), Some(30 * 24 + 1)] {
+            assert_eq!(
+                manage_action("extend", hours).unwrap_err().0,
+                "Invalid Expiry"
+            );
+        }
+        assert_eq!(
+            manage_action("delete", Some(24)).unwrap_err().0,
+            "Bad Action"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reveal_response() {
+        let response = reveal_response(
+            SecretReveal::Revealed {
+                ciphertext: "ciphertext".to_owned(),
+                file_ticket: Some("ticket".to_owned()),
+                receipt_email: None,
+            },
+            true,
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CACHE_CONTROL], "no-store");
+        assert_eq!(response.headers()[FILE_TICKET_HEADER], "ticket");
+        assert_eq!(body(response).await, "ciphertext");
+
+        let response = reveal_response(SecretReveal::WrongPassphrase { attempts_left: 2 }, true);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(body(response).await, "2");
+
+        let response = reveal_response(SecretReveal::NotRecipient, true);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = reveal_response(SecretReveal::Gone, true);
+        assert_eq!(response.status(), StatusCode::GONE);
+        let response = reveal_response(SecretReveal::Gone, false);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,5 +1,7 @@
 use askama::Template;
 
+use crate::data::Domain;
+
 #[derive(Template)]
 #[template(path = "../templates/content/shared/info.html")]
 pub struct InfoTemplate {
@@ -15,3 +17,29 @@ pub struct ErrorTemplate {
     pub message: String,
     pub back_path: String,
 }
+
+/// The host on which shortlinks of the default namespace are rendered, being the requested host,
+/// unless that is a custom domain, which only serves its own namespace,
+/// in which case the (first) public host is used instead.
+pub async fn default_host(state: &crate::router::State, request_host: &str) -> String {
+    if state
+        .storage
+        .get_domain(&Domain::normalize_host(request_host))
+        .await
+        .is_some()
+    {
+        if let Some(public_host) = state.public_hosts.first() {
+            return public_host.clone();
+        }
+    }
+    request_host.to_owned()
+}
+
+/// The scheme used to render shortlinks for the given host.
+pub fn scheme_for_host(host: &str) -> &'static str {
+    if host.to_lowercase().contains("bckt.xyz") {
+        "https"
+    } else {
+        "http"
+    }
+}
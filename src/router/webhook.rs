@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
+use axum::{
+    extract::{Host, State},
+    http::StatusCode,
+    response::Redirect,
+    Form,
+};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+
+use crate::data::{Shortlink, Webhook, WebhookDelivery};
+
+#[derive(Template)]
+#[template(path = "../templates/content/webhook.html")]
+pub struct GetTemplate {
+    pub email: String,
+    pub webhooks: Vec<(Webhook, Vec<WebhookDelivery>)>,
+    pub shortlinks: Vec<Shortlink>,
+    pub scheme: String,
+    pub host: String,
+}
+
+pub async fn get(
+    State(state): State<Arc<crate::router::State>>,
+    Host(host): Host,
+    cookies: Cookies,
+) -> Response {
+    if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
+        if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
+            let deliveries = state
+                .storage
+                .get_webhook_deliveries(identity.email_hash(), 100)
+                .await;
+            let webhooks = state
+                .storage
+                .get_webhooks_for_owner(identity.email_hash())
+                .await
+                .into_iter()
+                .map(|webhook| {
+                    let deliveries = deliveries
+                        .iter()
+                        .filter(|delivery| delivery.webhook_id() == webhook.id())
+                        .take(10)
+                        .cloned()
+                        .collect();
+                    (webhook, deliveries)
+                })
+                .collect();
+            let shortlinks = state
+                .storage
+                .get_shortlinks_for_owner(identity.email_hash())
+                .await;
+            let host = super::shared::default_host(&state, &host).await;
+            return GetTemplate {
+                email: identity.email().to_owned(),
+                webhooks,
+                shortlinks,
+                scheme: super::shared::scheme_for_host(&host).to_owned(),
+                host,
+            }
+            .into_response();
+        }
+    }
+    Redirect::temporary("/").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct PostParams {
+    action: String,
+    id: Option<i32>,
+    url: Option<String>,
+    /// The shortlink to notify clicks of, as `hash` or `domain/hash`,
+    /// or empty for all personal shortlinks.
+    link: Option<String>,
+}
+
+pub async fn post(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Form(params): Form<PostParams>,
+) -> Response {
+    let identity = match cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+    {
+        Some(identity) => identity,
+        None => {
+            return error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "You are not authorized for managing webhooks.".to_string(),
+            )
+        }
+    };
+
+    let result = match params.action.as_str() {
+        "create" => {
+            let url = params.url.unwrap_or_default().trim().to_owned();
+            let parsed = match reqwest::Url::parse(&url) {
+                Ok(parsed) => parsed,
+                Err(_) => {
+                    return error(
+                        StatusCode::BAD_REQUEST,
+                        "Invalid Webhook",
+                        format!("The webhook url '{url}' has to be an http(s) URL."),
+                    )
+                }
+            };
+            if let Err(err) = crate::services::net::check_public_url(&parsed).await {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid Webhook",
+                    format!("The webhook url '{url}' is not allowed: {err}."),
+                );
+            }
+            let link = params.link.filter(|link| !link.is_empty());
+            let link = link.as_deref().map(|link| match link.rsplit_once('/') {
+                Some((domain, hash)) => (hash, Some(domain)),
+                None => (link, None),
+            });
+            let secret = nanoid::nanoid!(32);
+            state
+                .storage
+                .add_webhook(identity.email_hash(), &url, &secret, link)
+                .await
+        }
+        "delete" => match params.id {
+            Some(id) => {
+                state
+                    .storage
+                    .delete_webhook(id, identity.email_hash())
+                    .await
+            }
+            None => {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "Webhook Missing",
+                    "Cannot delete a webhook without its id.".to_string(),
+                )
+            }
+        },
+        other => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "Bad Action",
+                format!("Invalid action {other}."),
+            )
+        }
+    };
+
+    match result {
+        Ok(()) => Redirect::to("/link/webhook").into_response(),
+        Err(err) => error(
+            StatusCode::BAD_REQUEST,
+            "Failed to Update Webhook",
+            format!("The webhook could not be updated: {err}."),
+        ),
+    }
+}
+
+fn error(status: StatusCode, title: &str, message: String) -> Response {
+    (
+        status,
+        super::shared::ErrorTemplate {
+            title: title.to_string(),
+            message,
+            back_path: "/link/webhook".to_string(),
+        },
+    )
+        .into_response()
+}
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
+use axum::{extract::State, http::StatusCode, response::Redirect, Form};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+
+use crate::data::{Workspace, WorkspaceMember, WorkspaceRole};
+
+#[derive(Template)]
+#[template(path = "../templates/content/workspace.html")]
+pub struct GetTemplate {
+    pub email: String,
+    pub email_hash: String,
+    pub workspaces: Vec<(Workspace, Vec<WorkspaceMember>)>,
+}
+
+pub async fn get(State(state): State<Arc<crate::router::State>>, cookies: Cookies) -> Response {
+    if let Some(cookie) = cookies.get(crate::services::COOKIE_NAME) {
+        if let Some(identity) = state.auth.verify_cookie(cookie.value()) {
+            let mut workspaces = Vec::new();
+            for workspace in state
+                .storage
+                .get_workspaces_for_member(identity.email_hash())
+                .await
+            {
+                let members = state.storage.get_workspace_members(workspace.id()).await;
+                workspaces.push((workspace, members));
+            }
+            return GetTemplate {
+                email: identity.email().to_owned(),
+                email_hash: identity.email_hash().to_owned(),
+                workspaces,
+            }
+            .into_response();
+        }
+    }
+    Redirect::temporary("/").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct PostParams {
+    action: String,
+    workspace: Option<i32>,
+    name: Option<String>,
+    email: Option<String>,
+    role: Option<String>,
+    member: Option<String>,
+}
+
+pub async fn post(
+    State(state): State<Arc<crate::router::State>>,
+    cookies: Cookies,
+    Form(params): Form<PostParams>,
+) -> Response {
+    let identity = match cookies
+        .get(crate::services::COOKIE_NAME)
+        .and_then(|cookie| state.auth.verify_cookie(cookie.value()))
+    {
+        Some(identity) => identity,
+        None => {
+            return error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "You are not authorized for managing workspaces.".to_string(),
+            )
+        }
+    };
+
+    if params.action == "create" {
+        let name = params.name.unwrap_or_default();
+        let name = name.trim();
+        if name.is_empty() || name.len() > 64 {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "Invalid Workspace Name",
+                "A workspace name is required and can be at most 64 characters long.".to_string(),
+            );
+        }
+        return match state
+            .storage
+            .add_workspace(name, identity.email_hash(), identity.email())
+            .await
+        {
+            Ok(id) => Redirect::to(&format!("/link?workspace={id}")).into_response(),
+            Err(err) => {
+                tracing::error!("Failed to create workspace {}: {}", name, err);
+                error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to Create Workspace",
+                    format!("The workspace '{name}' could not be created. Please try again later."),
+                )
+            }
+        };
+    }
+
+    let workspace = match params.workspace {
+        Some(workspace) => workspace,
+        None => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "Workspace Missing",
+                "No workspace was specified.".to_string(),
+            )
+        }
+    };
+    let role = match state
+        .storage
+        .get_workspace_role(workspace, identity.email_hash())
+        .await
+    {
+        Some(role) => role,
+        None => {
+            return error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "You are not a member of this workspace.".to_string(),
+            )
+        }
+    };
+
+    let result = match params.action.as_str() {
+        "leave" => {
+            state
+                .storage
+                .remove_workspace_member(workspace, identity.email_hash())
+                .await
+        }
+        action if !role.can_manage() => {
+            return error(
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                format!("Only owners of this workspace can perform the action {action}."),
+            )
+        }
+        "add_member" => {
+            let email = params.email.unwrap_or_default().trim().to_lowercase();
+            if email.is_empty() || !email.contains('@') {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid Email",
+                    "A valid email address is required to add a member.".to_string(),
+                );
+            }
+            let role: WorkspaceRole = match params.role.unwrap_or_default().parse() {
+                Ok(role) => role,
+                Err(err) => return error(StatusCode::BAD_REQUEST, "Invalid Role", err),
+            };
+            state
+                .storage
+                .set_workspace_member(
+                    workspace,
+                    &crate::services::hash_email(&email),
+                    &email,
+                    role,
+                )
+                .await
+        }
+        "remove_member" => {
+            let member = params.member.unwrap_or_default();
+            state
+                .storage
+                .remove_workspace_member(workspace, &member)
+                .await
+        }
+        "delete" => state.storage.delete_workspace(workspace).await,
+        other => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "Bad Action",
+                format!("Invalid action {other}."),
+            )
+        }
+    };
+
+    match result {
+        Ok(()) => Redirect::to("/workspace").into_response(),
+        Err(err) => error(
+            StatusCode::BAD_REQUEST,
+            "Failed to Update Workspace",
+            format!("The workspace could not be updated: {err}."),
+        ),
+    }
+}
+
+fn error(status: StatusCode, title: &str, message: String) -> Response {
+    (
+        status,
+        super::shared::ErrorTemplate {
+            title: title.to_string(),
+            message,
+            back_path: "/workspace".to_string(),
+        },
+    )
+        .into_response()
+}
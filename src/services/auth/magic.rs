@@ -5,6 +5,7 @@ use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 
 use super::email::EmailValidator;
+use crate::services::Mailer;
 
 base64_serde_type!(Base64Standard, general_purpose::URL_SAFE);
 
@@ -37,16 +38,15 @@ pub struct MagicIdentity {
     token: Vec<u8>,
     expires_at: u64,
     verified: bool,
+    /// The only resource this identity can be used for, if it is not a regular login.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
 }
 
 impl MagicIdentity {
     pub fn new(email: &str) -> Result<Self, String> {
         let email = email.to_lowercase();
-        let email_hash = hex::encode(
-            hash::digest(email.as_bytes())
-                .expect("hashing email")
-                .as_ref(),
-        );
+        let email_hash = super::hash_email(&email);
         let mut token = [0u8; 16];
         orion::util::secure_rand_bytes(&mut token).map_err(|e| e.to_string())?;
         let expires_at = chrono::Utc::now()
@@ -59,9 +59,16 @@ impl MagicIdentity {
             token: token.to_vec(),
             expires_at,
             verified: false,
+            scope: None,
         })
     }
 
+    /// Restrict the identity to a single resource, such as the secret of a recipient.
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
     pub fn decrypt(cipher: impl AsRef<str>, secret_key: &SecretKey) -> Result<Self, MagicError> {
         let cipher = cipher.as_ref();
         let cipher_bytes = general_purpose::URL_SAFE
@@ -89,6 +96,10 @@ impl MagicIdentity {
         &self.email_hash
     }
 
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+
     pub fn verified(&self) -> bool {
         self.verified
     }
@@ -117,14 +128,14 @@ impl MagicIdentity {
 
 #[derive(Debug)]
 pub struct MagicSender {
-    sendgrid_api_key: String,
+    mailer: Mailer,
     email_validator: EmailValidator,
 }
 
 impl MagicSender {
-    pub fn new(sendgrid_api_key: String, raw_auth_emails: String) -> Self {
+    pub fn new(mailer: Mailer, raw_auth_emails: String) -> Self {
         Self {
-            sendgrid_api_key,
+            mailer,
             email_validator: EmailValidator::new(raw_auth_emails),
         }
     }
@@ -158,72 +169,18 @@ impl MagicSender {
         })?;
 
         // send magic
-        let client = reqwest::Client::new();
-        let result = client
-            .post("https://api.sendgrid.com/v3/mail/send")
-            .header("Authorization", format!("Bearer {}", self.sendgrid_api_key))
-            .header("Content-Type", "application/json")
-            .json(&serde_json::json!({
-               "personalizations": [
-                  {
-                     "to": [
-                        {
-                           "email": email
-                        }
-                     ],
-                     "dynamic_template_data": {
-                        "magic": magic
-                     }
-                  }
-               ],
-               "from": {
-                  "email": "hello@bckt.xyz",
-                  "name": "bckt.xyz"
-               },
-               "reply_to": {
-                  "email": "hello@bckt.xyz",
-                  "name": "bckt.xyz"
-               },
-               "mail_settings": {
-                  "bypass_list_management": {
-                     "enable": false
-                  },
-                  "footer": {
-                     "enable": false
-                  },
-                  "sandbox_mode": {
-                     "enable": false
-                  }
-               },
-               "tracking_settings": {
-                  "click_tracking": {
-                     "enable": false,
-                     "enable_text": false
-                  },
-                  "open_tracking": {
-                     "enable": false
-                  },
-                  "subscription_tracking": {
-                     "enable": false
-                  }
-               },
-               "template_id": "d-3bf522f04d47411489abe38342be66a4"
-            }))
-            .send()
-            .await;
-        let resp = match result {
-            Err(e) => {
-                tracing::error!("Error: {:?}", e);
-                return Err((
-                    "Error sending magic link.".to_string(),
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                ));
-            }
-            Ok(response) => response,
-        };
-
-        if resp.status() != StatusCode::ACCEPTED {
-            tracing::error!("Error: {:?}: {}", resp.status(), resp.text().await.unwrap());
+        if let Err(e) = self
+            .mailer
+            .send_template(
+                email,
+                "d-3bf522f04d47411489abe38342be66a4",
+                serde_json::json!({
+                    "magic": magic
+                }),
+            )
+            .await
+        {
+            tracing::error!("Error: {}", e);
             return Err((
                 "Error sending magic link.".to_string(),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -247,6 +204,22 @@ mod tests {
         assert_eq!(magic, magic2);
     }
 
+    #[test]
+    fn test_magic_identity_scope() {
+        let key = SecretKey::default();
+        let magic = MagicIdentity::new("John@example.com")
+            .unwrap()
+            .with_scope("secret");
+        let magic = MagicIdentity::decrypt(magic.encrypt(&key).unwrap(), &key).unwrap();
+        assert_eq!(magic.scope(), Some("secret"));
+        assert_eq!(magic.email(), "john@example.com");
+
+        // identities issued before scopes existed are regular logins
+        let legacy = br#"{"email":"john@example.com","email_hash":"","token":"","expires_at":0,"verified":true}"#;
+        let cipher = general_purpose::URL_SAFE.encode(orion::aead::seal(&key, legacy).unwrap());
+        assert_eq!(MagicIdentity::decrypt(cipher, &key).unwrap().scope(), None);
+    }
+
     #[test]
     fn test_magic_identity_encrypt_different_nonce() {
         let magic = MagicIdentity::new("john@example.com").unwrap();
@@ -1,13 +1,25 @@
 use axum::http::StatusCode;
-use orion::aead::SecretKey;
+use orion::{aead::SecretKey, hash};
 
 use self::magic::MagicIdentity;
 
 pub const COOKIE_NAME: &str = "bckt-auth";
+/// Cookie of recipients logged in to read a single secret, set on the path of that secret.
+pub const RECIPIENT_COOKIE_NAME: &str = "bckt-recipient";
 
 mod email;
 mod magic;
 
+/// Hash an email address the same way identities are hashed,
+/// such that it can be matched against stored owner and member columns.
+pub fn hash_email(email: &str) -> String {
+    hex::encode(
+        hash::digest(email.to_lowercase().as_bytes())
+            .expect("hashing email")
+            .as_ref(),
+    )
+}
+
 #[derive(Debug)]
 pub struct Auth {
     secret_key: SecretKey,
@@ -15,12 +27,12 @@ pub struct Auth {
 }
 
 impl Auth {
-    pub fn new(private_key: String, raw_auth_emails: String, sendgrid_api_key: String) -> Self {
+    pub fn new(private_key: String, raw_auth_emails: String, mailer: super::Mailer) -> Self {
         let secret_key =
             SecretKey::from_slice(private_key.as_bytes()).expect("invalid private key");
         Self {
             secret_key,
-            magic_sender: magic::MagicSender::new(sendgrid_api_key, raw_auth_emails),
+            magic_sender: magic::MagicSender::new(mailer, raw_auth_emails),
         }
     }
 
@@ -30,7 +42,36 @@ impl Auth {
             .await
     }
 
+    /// Create a magic for the email which can only be used for the given scope,
+    /// such as reading a secret bound to its recipient.
+    ///
+    /// Unlike regular magic links these are not limited to the authorized emails,
+    /// as the identity cannot be used for anything else.
+    pub fn create_scoped_magic(&self, email: &str, scope: &str) -> Result<String, String> {
+        MagicIdentity::new(email)?
+            .with_scope(scope)
+            .encrypt(&self.secret_key)
+            .map_err(|e| e.to_string())
+    }
+
     pub fn verify_magic(&self, magic: impl AsRef<str>) -> Option<(String, u64)> {
+        self.verify_magic_for_scope(magic, None)
+    }
+
+    /// Verify a magic created using [`Auth::create_scoped_magic`] for the same scope.
+    pub fn verify_scoped_magic(
+        &self,
+        magic: impl AsRef<str>,
+        scope: &str,
+    ) -> Option<(String, u64)> {
+        self.verify_magic_for_scope(magic, Some(scope))
+    }
+
+    fn verify_magic_for_scope(
+        &self,
+        magic: impl AsRef<str>,
+        scope: Option<&str>,
+    ) -> Option<(String, u64)> {
         let mut identity = match magic::MagicIdentity::decrypt(magic, &self.secret_key) {
             Ok(identity) => identity,
             Err(e) => {
@@ -47,6 +88,10 @@ impl Auth {
             tracing::debug!("magic identity already verified");
             return None;
         }
+        if identity.scope() != scope {
+            tracing::debug!("magic identity used out of scope");
+            return None;
+        }
 
         // make it verified and allow it to be used for a fixed period of time
         identity.verify();
@@ -58,6 +103,23 @@ impl Auth {
     }
 
     pub fn verify_cookie(&self, magic: impl AsRef<str>) -> Option<MagicIdentity> {
+        self.verify_cookie_for_scope(magic, None)
+    }
+
+    /// Verify a cookie of an identity which can only be used for the given scope.
+    pub fn verify_scoped_cookie(
+        &self,
+        magic: impl AsRef<str>,
+        scope: &str,
+    ) -> Option<MagicIdentity> {
+        self.verify_cookie_for_scope(magic, Some(scope))
+    }
+
+    fn verify_cookie_for_scope(
+        &self,
+        magic: impl AsRef<str>,
+        scope: Option<&str>,
+    ) -> Option<MagicIdentity> {
         let identity = match magic::MagicIdentity::decrypt(magic, &self.secret_key) {
             Ok(identity) => identity,
             Err(e) => {
@@ -74,6 +136,10 @@ impl Auth {
             tracing::debug!("magic not yet verified");
             return None;
         }
+        if identity.scope() != scope {
+            tracing::debug!("magic used out of scope");
+            return None;
+        }
 
         Some(identity)
     }
@@ -0,0 +1,184 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// A bounded in-memory cache, where entries expire after a fixed time to live.
+///
+/// Absent values (`None`) can be cached as well, with their own (usually shorter)
+/// time to live, such that lookups of unknown keys are cached too.
+#[derive(Debug)]
+pub struct TtlCache<K, V> {
+    entries: Mutex<HashMap<K, Entry<V>>>,
+    capacity: usize,
+    ttl: Duration,
+    negative_ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: Option<V>,
+    expires_at: Instant,
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    pub fn new(capacity: usize, ttl: Duration, negative_ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::with_capacity(capacity)),
+            capacity,
+            ttl,
+            negative_ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Get a cached value, where `Some(None)` means the value is cached as absent,
+    /// and `None` that nothing (valid) is cached for the key.
+    pub fn get(&self, key: &K) -> Option<Option<V>> {
+        let mut entries = self.entries.lock().unwrap();
+        let result = match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+        match result {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        result
+    }
+
+    pub fn insert(&self, key: K, value: Option<V>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let expires_at = now
+            + if value.is_some() {
+                self.ttl
+            } else {
+                self.negative_ttl
+            };
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            // make room for an eighth of the capacity at once, such that the cost
+            // of this pass over all entries is spread over the inserts that follow
+            entries.retain(|_, entry| entry.expires_at > now);
+            let target = self.capacity - (self.capacity / 8).max(1);
+            if entries.len() > target {
+                let mut expiries = entries
+                    .values()
+                    .map(|entry| entry.expires_at)
+                    .collect::<Vec<_>>();
+                let (_, cutoff, _) = expiries.select_nth_unstable(entries.len() - target - 1);
+                let cutoff = *cutoff;
+                // evict the entries closest to their expiry
+                entries.retain(|_, entry| entry.expires_at > cutoff);
+            }
+        }
+        entries.insert(key, Entry { value, expires_at });
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    /// Invalidate all entries for which the predicate holds.
+    pub fn invalidate_where(&self, predicate: impl Fn(&K) -> bool) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|key, _| !predicate(key));
+    }
+
+    pub fn size(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ttl_cache_hit_and_miss() {
+        let cache = TtlCache::new(10, Duration::from_secs(60), Duration::from_secs(60));
+        assert_eq!(cache.get(&"a"), None);
+        cache.insert("a", Some(1));
+        cache.insert("b", None);
+        assert_eq!(cache.get(&"a"), Some(Some(1)));
+        assert_eq!(cache.get(&"b"), Some(None));
+        assert_eq!(cache.hits(), 2);
+        assert_eq!(cache.misses(), 1);
+    }
+
+    #[test]
+    fn test_ttl_cache_expiry() {
+        let cache: TtlCache<_, ()> = TtlCache::new(10, Duration::from_secs(60), Duration::ZERO);
+        cache.insert("a", None);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn test_ttl_cache_invalidate() {
+        let cache = TtlCache::new(10, Duration::from_secs(60), Duration::from_secs(60));
+        cache.insert(("x", "a"), Some(1));
+        cache.insert(("x", "b"), Some(2));
+        cache.insert(("y", "a"), Some(3));
+        cache.invalidate(&("x", "a"));
+        assert_eq!(cache.get(&("x", "a")), None);
+        cache.invalidate_where(|(domain, _)| *domain == "x");
+        assert_eq!(cache.get(&("x", "b")), None);
+        assert_eq!(cache.get(&("y", "a")), Some(Some(3)));
+    }
+
+    #[test]
+    fn test_ttl_cache_bounded() {
+        let cache = TtlCache::new(2, Duration::from_secs(60), Duration::from_secs(30));
+        cache.insert(1, None);
+        cache.insert(2, Some(2));
+        cache.insert(3, Some(3));
+        assert_eq!(cache.size(), 2);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&3), Some(Some(3)));
+
+        let cache = TtlCache::new(16, Duration::from_secs(60), Duration::from_secs(30));
+        // the absent values expire first
+        cache.insert(0, None);
+        cache.insert(1, None);
+        for key in 2..16 {
+            cache.insert(key, Some(key));
+        }
+        cache.insert(16, Some(16));
+        // an eighth of the entries is evicted at once, closest to their expiry first
+        assert_eq!(cache.size(), 15);
+        assert_eq!(cache.get(&0), None);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some(Some(2)));
+        assert_eq!(cache.get(&16), Some(Some(16)));
+    }
+}
@@ -0,0 +1,428 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use askama::Template;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use futures_util::StreamExt;
+use reqwest::Url;
+
+use super::{Mailer, Storage};
+
+/// The amount of days summarized by a digest.
+const DIGEST_DAYS: i32 = 7;
+
+/// The maximum amount of destinations checked for a single digest.
+const MAX_CHECKED_LINKS: usize = 50;
+
+/// The maximum amount of destinations checked at the same time.
+const CONCURRENT_CHECKS: usize = 8;
+
+/// Checks whether or not the destination of a shortlink still exists.
+#[async_trait]
+pub trait LinkChecker: std::fmt::Debug + Send + Sync {
+    async fn is_dead(&self, url: &Url) -> bool;
+}
+
+/// Checks destinations over http(s), where only a `404 Not Found` or `410 Gone`
+/// marks them as dead, as other failures might as well be temporary.
+///
+/// Destinations are only checked if they are public addresses.
+#[derive(Debug)]
+pub struct HttpLinkChecker {
+    client: reqwest::Client,
+}
+
+impl HttpLinkChecker {
+    pub fn new(timeout: Duration) -> Self {
+        let client = super::net::guarded_client(10)
+            .timeout(timeout)
+            .user_agent("bckt.xyz (+https://bckt.xyz/code)")
+            .build()
+            .expect("build link checker http client");
+        Self { client }
+    }
+}
+
+impl Default for HttpLinkChecker {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+#[async_trait]
+impl LinkChecker for HttpLinkChecker {
+    async fn is_dead(&self, url: &Url) -> bool {
+        if !super::net::is_public_url(url) {
+            return false;
+        }
+        match self.client.get(url.clone()).send().await {
+            Ok(response) => {
+                let status = response.status();
+                status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::GONE
+            }
+            Err(e) => {
+                tracing::debug!("check destination {}: {}", url, e);
+                false
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DigestLink {
+    pub short: String,
+    pub long: String,
+    pub title: Option<String>,
+    pub clicks: i64,
+    pub clicks_total: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DigestReferrer {
+    pub short: String,
+    pub host: String,
+}
+
+/// The activity of the personal shortlinks of a user over the last week.
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub links: Vec<DigestLink>,
+    pub referrers: Vec<DigestReferrer>,
+    pub dead: Vec<DigestLink>,
+    pub manage_url: String,
+}
+
+impl Digest {
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty() && self.referrers.is_empty() && self.dead.is_empty()
+    }
+
+    pub fn total_clicks(&self) -> i64 {
+        self.links.iter().map(|link| link.clicks).sum()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "../templates/email/digest.html")]
+pub struct DigestHtmlTemplate<'a> {
+    pub digest: &'a Digest,
+}
+
+#[derive(Template)]
+#[template(path = "../templates/email/digest.txt")]
+pub struct DigestTextTemplate<'a> {
+    pub digest: &'a Digest,
+}
+
+/// Check the destinations of the links a few at a time, returning the dead ones in order.
+async fn find_dead(checker: &dyn LinkChecker, links: Vec<(Url, DigestLink)>) -> Vec<DigestLink> {
+    futures_util::stream::iter(links)
+        .map(|(url, link)| async move { checker.is_dead(&url).await.then_some(link) })
+        .buffered(CONCURRENT_CHECKS)
+        .filter_map(|link| async move { link })
+        .collect()
+        .await
+}
+
+/// The next moment the digests are sent, which is every Monday at 08:00 UTC.
+pub fn next_run(now: DateTime<Utc>) -> DateTime<Utc> {
+    let time = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+    let mut date = now.date_naive();
+    loop {
+        let run = date.and_time(time).and_utc();
+        if date.weekday() == Weekday::Mon && run > now {
+            return run;
+        }
+        date = date.succ_opt().unwrap();
+    }
+}
+
+/// Renders and sends the weekly digests to the users which opted in to them.
+///
+/// In dry-run mode digests are rendered and logged, but never sent.
+#[derive(Debug)]
+pub struct Digests {
+    storage: Storage,
+    mailer: Mailer,
+    checker: Arc<dyn LinkChecker>,
+    host: String,
+    dry_run: bool,
+}
+
+impl Digests {
+    pub fn new(
+        storage: Storage,
+        mailer: Mailer,
+        checker: Arc<dyn LinkChecker>,
+        host: String,
+        dry_run: bool,
+    ) -> Self {
+        Self {
+            storage,
+            mailer,
+            checker,
+            host,
+            dry_run,
+        }
+    }
+
+    /// Collect the digest of the personal shortlinks of the owner,
+    /// checking whether their destinations still exist only if asked to,
+    /// as that takes a request per destination.
+    pub async fn collect(&self, owner_email: &str, check_dead: bool) -> Digest {
+        let shortlinks = self.storage.get_shortlinks_for_owner(owner_email).await;
+        let clicks: HashMap<_, _> = self
+            .storage
+            .get_recent_clicks_for_owner(owner_email, DIGEST_DAYS)
+            .await
+            .into_iter()
+            .map(|(hash, domain, clicks)| ((hash, domain), clicks))
+            .collect();
+        let short = |hash: &str, domain: Option<&str>| {
+            shortlinks
+                .iter()
+                .find(|shortlink| shortlink.link_hash() == hash && shortlink.domain() == domain)
+                .map(|shortlink| shortlink.link_short("https", &self.host))
+        };
+
+        let mut links = Vec::new();
+        let mut checked = Vec::new();
+        for (index, shortlink) in shortlinks.iter().enumerate() {
+            let link = DigestLink {
+                short: shortlink.link_short("https", &self.host),
+                long: shortlink.link_canonical().to_owned(),
+                title: shortlink
+                    .title()
+                    .or(shortlink.page_title())
+                    .map(str::to_owned),
+                clicks: clicks
+                    .get(&(
+                        shortlink.link_hash().to_owned(),
+                        shortlink.domain().map(str::to_owned),
+                    ))
+                    .copied()
+                    .unwrap_or_default(),
+                clicks_total: shortlink.clicks(),
+            };
+            if check_dead && index < MAX_CHECKED_LINKS {
+                if let Ok(url) = Url::parse(shortlink.link_canonical()) {
+                    checked.push((url, link.clone()));
+                }
+            }
+            if link.clicks > 0 {
+                links.push(link);
+            }
+        }
+        links.sort_by_key(|link| std::cmp::Reverse(link.clicks));
+
+        let dead = find_dead(self.checker.as_ref(), checked).await;
+
+        let referrers = self
+            .storage
+            .get_new_referrers_for_owner(owner_email, DIGEST_DAYS)
+            .await
+            .into_iter()
+            .filter_map(|(hash, domain, host)| {
+                short(&hash, domain.as_deref()).map(|short| DigestReferrer { short, host })
+            })
+            .collect();
+
+        Digest {
+            links,
+            referrers,
+            dead,
+            manage_url: format!("https://{}/link/digest", self.host),
+        }
+    }
+
+    /// Send the digests of all users which opted in and did not receive one this week.
+    pub async fn send_due(&self) {
+        for (owner_email, email) in self.storage.get_digest_subscribers_due().await {
+            let digest = self.collect(&owner_email, true).await;
+            if !digest.is_empty() {
+                let (html, text) = match (
+                    DigestHtmlTemplate { digest: &digest }.render(),
+                    DigestTextTemplate { digest: &digest }.render(),
+                ) {
+                    (Ok(html), Ok(text)) => (html, text),
+                    (Err(e), _) | (_, Err(e)) => {
+                        tracing::error!("Failed to render digest for {}: {}", owner_email, e);
+                        continue;
+                    }
+                };
+                if self.dry_run {
+                    tracing::info!("dry run: digest for {}:\n{}", owner_email, text);
+                    continue;
+                }
+                if let Err(e) = self
+                    .mailer
+                    .send_html(&email, "Your week on bckt.xyz", &html, &text)
+                    .await
+                {
+                    tracing::error!("Failed to send digest to {}: {}", owner_email, e);
+                    continue;
+                }
+            }
+            if let Err(e) = self.storage.set_digest_sent(&owner_email).await {
+                tracing::error!("Failed to mark digest of {} as sent: {}", owner_email, e);
+            }
+        }
+    }
+
+    /// Send the digests every week, starting right away in case this week's run was missed.
+    pub fn spawn(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let now = Utc::now();
+            if now.weekday() == Weekday::Mon && next_run(now).date_naive() != now.date_naive() {
+                self.send_due().await;
+            }
+            loop {
+                let wait = (next_run(Utc::now()) - Utc::now())
+                    .to_std()
+                    .unwrap_or_default();
+                tokio::time::sleep(wait).await;
+                self.send_due().await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use chrono::TimeZone;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    /// Marks destinations ending in `/dead` as dead, tracking how many are checked at once.
+    #[derive(Debug, Default)]
+    struct StubLinkChecker {
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LinkChecker for StubLinkChecker {
+        async fn is_dead(&self, url: &Url) -> bool {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            url.path() == "/dead"
+        }
+    }
+
+    fn link(long: &str) -> DigestLink {
+        DigestLink {
+            short: "https://bckt.xyz/abcd1234".to_owned(),
+            long: long.to_owned(),
+            title: None,
+            clicks: 0,
+            clicks_total: 0,
+        }
+    }
+
+    #[test]
+    fn test_next_run() {
+        // a wednesday
+        let now = Utc.with_ymd_and_hms(2023, 11, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            next_run(now),
+            Utc.with_ymd_and_hms(2023, 11, 6, 8, 0, 0).unwrap()
+        );
+        // a monday, before and after the digests are sent
+        let now = Utc.with_ymd_and_hms(2023, 11, 6, 7, 59, 0).unwrap();
+        assert_eq!(
+            next_run(now),
+            Utc.with_ymd_and_hms(2023, 11, 6, 8, 0, 0).unwrap()
+        );
+        let now = Utc.with_ymd_and_hms(2023, 11, 6, 8, 0, 0).unwrap();
+        assert_eq!(
+            next_run(now),
+            Utc.with_ymd_and_hms(2023, 11, 13, 8, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_dead() {
+        let checker = StubLinkChecker::default();
+        let links = (0..20)
+            .map(|index| {
+                let long = if index % 3 == 0 {
+                    format!("https://{index}.example.com/dead")
+                } else {
+                    format!("https://{index}.example.com/")
+                };
+                (Url::parse(&long).unwrap(), link(&long))
+            })
+            .collect();
+        let dead = find_dead(&checker, links).await;
+        assert_eq!(
+            dead.iter()
+                .map(|link| link.long.as_str())
+                .collect::<Vec<_>>(),
+            (0..20)
+                .filter(|index| index % 3 == 0)
+                .map(|index| format!("https://{index}.example.com/dead"))
+                .collect::<Vec<_>>()
+        );
+        let max_active = checker.max_active.load(Ordering::SeqCst);
+        assert!(max_active > 1 && max_active <= CONCURRENT_CHECKS);
+    }
+
+    #[tokio::test]
+    async fn test_http_link_checker_refuses_private_addresses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let _ = stream
+                .write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await;
+        });
+
+        // a destination which cannot be checked is not reported as dead
+        let checker = HttpLinkChecker::default();
+        for url in [
+            format!("http://127.0.0.1:{port}/gone"),
+            format!("http://localhost:{port}/gone"),
+        ] {
+            assert!(!checker.is_dead(&Url::parse(&url).unwrap()).await);
+        }
+    }
+
+    #[test]
+    fn test_render_digest() {
+        let link = DigestLink {
+            short: "https://bckt.xyz/abcd1234".to_owned(),
+            long: "https://example.com/".to_owned(),
+            title: Some("Example <Domain>".to_owned()),
+            clicks: 3,
+            clicks_total: 10,
+        };
+        let digest = Digest {
+            links: vec![link.clone()],
+            referrers: vec![DigestReferrer {
+                short: link.short.clone(),
+                host: "news.ycombinator.com".to_owned(),
+            }],
+            dead: vec![link],
+            manage_url: "https://bckt.xyz/link/digest".to_owned(),
+        };
+        assert!(!digest.is_empty());
+        assert_eq!(digest.total_clicks(), 3);
+
+        let html = DigestHtmlTemplate { digest: &digest }.render().unwrap();
+        assert!(html.contains("https://bckt.xyz/abcd1234"));
+        assert!(html.contains("Example &lt;Domain&gt;"));
+        assert!(html.contains("news.ycombinator.com"));
+
+        let text = DigestTextTemplate { digest: &digest }.render().unwrap();
+        assert!(text.contains("Example <Domain>"));
+        assert!(text.contains("3 clicks"));
+    }
+}
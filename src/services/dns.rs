@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// Looks up the TXT records of a name, e.g. to verify the ownership of a domain.
+#[async_trait]
+pub trait TxtResolver: std::fmt::Debug + Send + Sync {
+    async fn txt_records(&self, name: &str) -> Result<Vec<String>, String>;
+}
+
+/// Looks up TXT records using the JSON API of a DNS-over-HTTPS resolver,
+/// such as the one of Cloudflare.
+#[derive(Debug)]
+pub struct DohTxtResolver {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl DohTxtResolver {
+    pub fn new(endpoint: String, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent("bckt.xyz (+https://bckt.xyz/code)")
+            .build()
+            .expect("build dns http client");
+        Self { client, endpoint }
+    }
+}
+
+impl Default for DohTxtResolver {
+    fn default() -> Self {
+        Self::new(
+            "https://cloudflare-dns.com/dns-query".to_owned(),
+            Duration::from_secs(3),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    kind: u16,
+    data: String,
+}
+
+/// The DNS record type of TXT records.
+const TXT: u16 = 16;
+
+/// Get the TXT records from a DNS JSON response, joining the quoted strings of each record.
+fn parse_txt_records(response: DohResponse) -> Result<Vec<String>, String> {
+    // NXDOMAIN simply means that there are no records
+    if response.status != 0 && response.status != 3 {
+        return Err(format!("dns lookup failed with status {}", response.status));
+    }
+    Ok(response
+        .answer
+        .into_iter()
+        .filter(|answer| answer.kind == TXT)
+        .map(|answer| {
+            answer
+                .data
+                .split('"')
+                .skip(1)
+                .step_by(2)
+                .collect::<String>()
+        })
+        .collect())
+}
+
+#[async_trait]
+impl TxtResolver for DohTxtResolver {
+    async fn txt_records(&self, name: &str) -> Result<Vec<String>, String> {
+        let response = self
+            .client
+            .get(&self.endpoint)
+            .query(&[("name", name), ("type", "TXT")])
+            .header(reqwest::header::ACCEPT, "application/dns-json")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json::<DohResponse>()
+            .await
+            .map_err(|e| e.to_string())?;
+        parse_txt_records(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_txt_records() {
+        let response: DohResponse = serde_json::from_str(
+            r#"{"Status":0,"Answer":[
+                {"name":"_bckt-challenge.go.example.com","type":16,"TTL":300,"data":"\"bckt-verification=abc\""},
+                {"name":"_bckt-challenge.go.example.com","type":16,"TTL":300,"data":"\"split \" \"record\""},
+                {"name":"go.example.com","type":5,"TTL":300,"data":"example.com."}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parse_txt_records(response).unwrap(),
+            vec!["bckt-verification=abc", "split record"]
+        );
+
+        let response: DohResponse = serde_json::from_str(r#"{"Status":3}"#).unwrap();
+        assert!(parse_txt_records(response).unwrap().is_empty());
+        let response: DohResponse = serde_json::from_str(r#"{"Status":2}"#).unwrap();
+        assert!(parse_txt_records(response).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use std::{collections::BTreeSet, fmt};
+
+/// Normalize a (possibly internationalised) host name to its ASCII (punycode) form,
+/// which is how hosts are stored and matched against blocklists.
+pub fn to_ascii(host: &str) -> Option<String> {
+    idna::domain_to_ascii(host)
+        .ok()
+        .filter(|host| !host.is_empty())
+}
+
+/// Render a host name in its Unicode form, which is how people read it.
+pub fn to_unicode(host: &str) -> String {
+    match idna::domain_to_unicode(host) {
+        (unicode, Ok(())) => unicode,
+        _ => host.to_owned(),
+    }
+}
+
+/// Render a url with its host in Unicode form, for display purposes only.
+pub fn display_url(url: &str) -> String {
+    let host = match reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+    {
+        Some(host) if host.split('.').any(|label| label.starts_with("xn--")) => host,
+        _ => return url.to_owned(),
+    };
+    match url.split_once(&host) {
+        Some((before, after)) => format!("{}{}{}", before, to_unicode(&host), after),
+        None => url.to_owned(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Thai,
+    Han,
+    Hiragana,
+    Katakana,
+    Hangul,
+    Bopomofo,
+    Other,
+}
+
+fn script(c: char) -> Option<Script> {
+    Some(match c {
+        // digits, hyphens and combining marks are shared by all scripts
+        '0'..='9' | '-' | '\u{0300}'..='\u{036F}' => return None,
+        'a'..='z' | 'A'..='Z' => Script::Latin,
+        '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' => Script::Latin,
+        '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Script::Greek,
+        '\u{0400}'..='\u{052F}' => Script::Cyrillic,
+        '\u{0530}'..='\u{058F}' => Script::Armenian,
+        '\u{0590}'..='\u{05FF}' => Script::Hebrew,
+        '\u{0600}'..='\u{06FF}' => Script::Arabic,
+        '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+        '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' => Script::Han,
+        '\u{3040}'..='\u{309F}' => Script::Hiragana,
+        '\u{30A0}'..='\u{30FF}' => Script::Katakana,
+        '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' => Script::Hangul,
+        '\u{3100}'..='\u{312F}' => Script::Bopomofo,
+        _ => Script::Other,
+    })
+}
+
+/// Scripts which are commonly mixed within a single label (e.g. in Japanese).
+const ALLOWED_MIXES: &[&[Script]] = &[
+    &[
+        Script::Latin,
+        Script::Han,
+        Script::Hiragana,
+        Script::Katakana,
+    ],
+    &[Script::Latin, Script::Han, Script::Hangul],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+];
+
+/// Non-Latin letters which are (near) indistinguishable from Latin ones.
+fn confusable(c: char) -> Option<char> {
+    Some(match c {
+        // cyrillic
+        'а' => 'a',
+        'е' => 'e',
+        'о' => 'o',
+        'р' => 'p',
+        'с' => 'c',
+        'у' | 'ү' => 'y',
+        'х' => 'x',
+        'і' => 'i',
+        'ј' => 'j',
+        'ѕ' => 's',
+        'һ' => 'h',
+        'ԁ' => 'd',
+        'ԛ' => 'q',
+        'ԝ' => 'w',
+        'ӏ' => 'l',
+        'ь' => 'b',
+        // greek
+        'α' => 'a',
+        'ο' => 'o',
+        'ν' => 'v',
+        'ρ' => 'p',
+        'υ' => 'u',
+        'ι' => 'i',
+        'κ' => 'k',
+        'χ' => 'x',
+        _ => return None,
+    })
+}
+
+/// A warning about a host name which might be impersonating another one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HomographWarning {
+    /// The host name in its Unicode form.
+    host: String,
+    /// The Latin host name this host name can be mistaken for, if any.
+    lookalike: Option<String>,
+    mixed_script: bool,
+}
+
+impl fmt::Display for HomographWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mixed_script {
+            write!(f, "The domain '{}' mixes different scripts", self.host)?;
+        } else {
+            write!(
+                f,
+                "The domain '{}' only uses letters that look like Latin ones",
+                self.host
+            )?;
+        }
+        match &self.lookalike {
+            Some(lookalike) => write!(f, " and can be mistaken for '{}'.", lookalike),
+            None => write!(f, "."),
+        }
+    }
+}
+
+/// Detect hosts with mixed-script labels, or with labels entirely made out of
+/// letters that look like Latin ones, which are typically used to impersonate brands.
+pub fn homograph_warning(host: &str) -> Option<HomographWarning> {
+    let host = to_unicode(host);
+    let mut mixed_script = false;
+    let mut whole_confusable = false;
+    for label in host.split('.') {
+        let scripts: BTreeSet<Script> = label.chars().filter_map(script).collect();
+        if scripts.len() > 1
+            && !ALLOWED_MIXES
+                .iter()
+                .any(|mix| scripts.iter().all(|script| mix.contains(script)))
+        {
+            mixed_script = true;
+        } else if scripts.len() == 1
+            && !scripts.contains(&Script::Latin)
+            && label
+                .chars()
+                .all(|c| script(c).is_none() || confusable(c).is_some())
+        {
+            whole_confusable = true;
+        }
+    }
+    if !mixed_script && !whole_confusable {
+        return None;
+    }
+
+    let skeleton: String = host.chars().map(|c| confusable(c).unwrap_or(c)).collect();
+    let lookalike = if skeleton != host && skeleton.is_ascii() {
+        Some(skeleton)
+    } else {
+        None
+    };
+    Some(HomographWarning {
+        host,
+        lookalike,
+        mixed_script,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_ascii_and_back() {
+        assert_eq!(
+            to_ascii("Bücher.example").as_deref(),
+            Some("xn--bcher-kva.example")
+        );
+        assert_eq!(to_ascii("example.com").as_deref(), Some("example.com"));
+        assert_eq!(to_unicode("xn--bcher-kva.example"), "bücher.example");
+        assert_eq!(to_ascii(""), None);
+    }
+
+    #[test]
+    fn test_display_url() {
+        assert_eq!(
+            display_url("https://xn--bcher-kva.example/xn--a?q=1"),
+            "https://bücher.example/xn--a?q=1"
+        );
+        assert_eq!(
+            display_url("https://example.com/a"),
+            "https://example.com/a"
+        );
+    }
+
+    #[test]
+    fn test_homograph_warning_none() {
+        assert_eq!(homograph_warning("example.com"), None);
+        assert_eq!(homograph_warning("xn--bcher-kva.example"), None);
+        assert_eq!(homograph_warning("пример.рф"), None);
+        assert_eq!(homograph_warning("日本語のドメイン.jp"), None);
+    }
+
+    #[test]
+    fn test_homograph_warning_mixed_script() {
+        // latin "pay" with a cyrillic "р" and "а"
+        let warning = homograph_warning(&to_ascii("раypal.com").unwrap()).unwrap();
+        assert!(warning.mixed_script);
+        assert_eq!(warning.host, "раypal.com");
+        assert_eq!(warning.lookalike.as_deref(), Some("paypal.com"));
+    }
+
+    #[test]
+    fn test_homograph_warning_whole_script() {
+        // all cyrillic look-alikes of "apple"
+        let warning = homograph_warning("аррӏе.com").unwrap();
+        assert!(!warning.mixed_script);
+        assert_eq!(warning.lookalike.as_deref(), Some("apple.com"));
+        assert!(warning.to_string().contains("apple.com"));
+    }
+}
@@ -0,0 +1,133 @@
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+/// Sends emails from `hello@bckt.xyz` using SendGrid.
+#[derive(Debug, Clone)]
+pub struct Mailer {
+    sendgrid_api_key: String,
+    client: reqwest::Client,
+}
+
+impl Mailer {
+    pub fn new(sendgrid_api_key: String) -> Self {
+        Self {
+            sendgrid_api_key,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Send an email rendered by SendGrid from one of its dynamic templates.
+    pub async fn send_template(
+        &self,
+        email: &str,
+        template_id: &str,
+        template_data: Value,
+    ) -> Result<(), String> {
+        self.send(json!({
+           "personalizations": [
+              {
+                 "to": [
+                    {
+                       "email": email
+                    }
+                 ],
+                 "dynamic_template_data": template_data
+              }
+           ],
+           "template_id": template_id
+        }))
+        .await
+    }
+
+    /// Send an email of which the (html) content is rendered by ourselves.
+    pub async fn send_html(
+        &self,
+        email: &str,
+        subject: &str,
+        html: &str,
+        text: &str,
+    ) -> Result<(), String> {
+        self.send(json!({
+           "personalizations": [
+              {
+                 "to": [
+                    {
+                       "email": email
+                    }
+                 ]
+              }
+           ],
+           "subject": subject,
+           "content": [
+              {
+                 "type": "text/plain",
+                 "value": text
+              },
+              {
+                 "type": "text/html",
+                 "value": html
+              }
+           ]
+        }))
+        .await
+    }
+
+    async fn send(&self, mut message: Value) -> Result<(), String> {
+        let settings = json!({
+           "from": {
+              "email": "hello@bckt.xyz",
+              "name": "bckt.xyz"
+           },
+           "reply_to": {
+              "email": "hello@bckt.xyz",
+              "name": "bckt.xyz"
+           },
+           "mail_settings": {
+              "bypass_list_management": {
+                 "enable": false
+              },
+              "footer": {
+                 "enable": false
+              },
+              "sandbox_mode": {
+                 "enable": false
+              }
+           },
+           "tracking_settings": {
+              "click_tracking": {
+                 "enable": false,
+                 "enable_text": false
+              },
+              "open_tracking": {
+                 "enable": false
+              },
+              "subscription_tracking": {
+                 "enable": false
+              }
+           }
+        });
+        if let (Some(message), Value::Object(settings)) = (message.as_object_mut(), settings) {
+            message.extend(settings);
+        }
+
+        let resp = self
+            .client
+            .post("https://api.sendgrid.com/v3/mail/send")
+            .header("Authorization", format!("Bearer {}", self.sendgrid_api_key))
+            .header("Content-Type", "application/json")
+            .json(&message)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if resp.status() != StatusCode::ACCEPTED {
+            return Err(format!(
+                "{}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,39 @@
 mod auth;
-pub use auth::{Auth, COOKIE_NAME};
+pub use auth::{hash_email, Auth, COOKIE_NAME, RECIPIENT_COOKIE_NAME};
+
+pub mod blob;
+pub use blob::{BlobStore, LocalBlobStore, S3BlobStore};
+
+mod cache;
+pub use cache::TtlCache;
+
+pub mod digest;
+pub use digest::{Digests, HttpLinkChecker};
+
+pub mod dns;
+pub use dns::{DohTxtResolver, TxtResolver};
+
+pub mod idn;
+
+mod mail;
+pub use mail::Mailer;
+
+pub mod net;
+
+mod page;
+pub use page::{HttpPageFetcher, PageFetcher, PageMeta};
+
+pub mod rate_limit;
+
+pub mod secret;
+
+pub mod shortener;
+pub use shortener::{HttpShortenerResolver, ShortenerResolver};
 
 mod storage;
 pub use storage::Storage;
+
+pub mod user_agent;
+
+pub mod webhook;
+pub use webhook::WebhookDispatcher;
@@ -0,0 +1,207 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    redirect, Url,
+};
+
+/// Whether or not the address is reachable over the public internet,
+/// as opposed to loopback, private, link-local (e.g. cloud metadata) and reserved ranges.
+pub fn is_global_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_global_ipv4(ip),
+        IpAddr::V6(ip) => is_global_ipv6(ip),
+    }
+}
+
+fn is_global_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "this network", shared address space (carrier-grade NAT) and benchmarking
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (18..20).contains(&b))
+        // IETF protocol assignments and reserved for future use
+        || (a == 192 && b == 0 && c == 0)
+        || a >= 240)
+}
+
+fn is_global_ipv6(ip: Ipv6Addr) -> bool {
+    // addresses embedding an IPv4 address are as reachable as that address
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        return is_global_ipv4(ip);
+    }
+    let segments = ip.segments();
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [a, b] = segments[6].to_be_bytes();
+        let [c, d] = segments[7].to_be_bytes();
+        return is_global_ipv4(Ipv4Addr::new(a, b, c, d));
+    }
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // unique local, link-local and documentation
+        || (segments[0] & 0xfe00) == 0xfc00
+        || (segments[0] & 0xffc0) == 0xfe80
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+        // deprecated IPv4-compatible addresses
+        || segments[..6] == [0, 0, 0, 0, 0, 0])
+}
+
+/// Whether or not a user-chosen url may be requested by the server:
+/// it has to be http(s), and an IP address in it has to be global.
+///
+/// Domains are checked once they are resolved, by the clients of [`guarded_client`].
+pub fn is_public_url(url: &Url) -> bool {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return false;
+    }
+    match url.host() {
+        Some(url::Host::Domain(_)) => true,
+        Some(url::Host::Ipv4(ip)) => is_global_ipv4(ip),
+        Some(url::Host::Ipv6(ip)) => is_global_ipv6(ip),
+        None => false,
+    }
+}
+
+/// Resolve the host of a user-chosen url upfront, failing unless all of its addresses are global,
+/// such that urls stored for later use can be rejected right away.
+pub async fn check_public_url(url: &Url) -> Result<(), String> {
+    if !is_public_url(url) {
+        return Err("the url has to be http(s) and point to a public address".to_owned());
+    }
+    let host = match url.host() {
+        Some(url::Host::Domain(domain)) => domain,
+        _ => return Ok(()),
+    };
+    let addrs = tokio::net::lookup_host((host, url.port_or_known_default().unwrap_or(80)))
+        .await
+        .map_err(|e| format!("the host {host} could not be resolved: {e}"))?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_global_ip(addr.ip())) {
+        return Err(format!(
+            "the host {host} does not resolve to a public address"
+        ));
+    }
+    Ok(())
+}
+
+/// Resolves hosts the way the system does, leaving out addresses which are not global,
+/// such that a domain cannot be pointed (or rebound) to an internal service.
+#[derive(Debug, Default)]
+struct GlobalResolver;
+
+impl Resolve for GlobalResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_global_ip(addr.ip()))
+                .collect::<Vec<SocketAddr>>();
+            if addrs.is_empty() {
+                return Err(
+                    format!("{} does not resolve to a public address", name.as_str()).into(),
+                );
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// A client builder for requests to user-chosen urls, which never connects to addresses
+/// which are not global, also not when following (at most `max_redirects`) redirects.
+///
+/// The url of the first request still has to be checked using [`is_public_url`].
+pub fn guarded_client(max_redirects: usize) -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .no_proxy()
+        .dns_resolver(Arc::new(GlobalResolver))
+        .redirect(redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_redirects {
+                attempt.error("too many redirects")
+            } else if !is_public_url(attempt.url()) {
+                attempt.error("redirect to a non-public address")
+            } else {
+                attempt.follow()
+            }
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_global_ip() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!is_global_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "1.1.1.1",
+            "93.184.216.34",
+            "2606:4700:4700::1111",
+            "::ffff:1.1.1.1",
+        ] {
+            assert!(is_global_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_public_url() {
+        assert!(check_public_url(&Url::parse("http://127.0.0.1/").unwrap())
+            .await
+            .is_err());
+        assert!(check_public_url(&Url::parse("http://localhost/").unwrap())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_is_public_url() {
+        for url in [
+            "https://example.com/a",
+            "http://1.1.1.1/",
+            "https://[2606:4700:4700::1111]/",
+        ] {
+            assert!(is_public_url(&Url::parse(url).unwrap()), "{url}");
+        }
+        for url in [
+            "ftp://example.com/",
+            "http://127.0.0.1:8000/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]/",
+            "http://[::ffff:10.0.0.1]/",
+            "http://2130706433/",
+        ] {
+            assert!(!is_public_url(&Url::parse(url).unwrap()), "{url}");
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{header::CONTENT_TYPE, Url};
+
+/// Metadata of the page a shortlink points to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageMeta {
+    pub title: Option<String>,
+    pub favicon: Option<String>,
+}
+
+/// Fetches the metadata of the destination page of a shortlink.
+#[async_trait]
+pub trait PageFetcher: std::fmt::Debug + Send + Sync {
+    async fn fetch(&self, url: &Url) -> Option<PageMeta>;
+}
+
+/// Fetches page metadata over http(s), reading at most `max_bytes`
+/// of the page within the given timeout.
+///
+/// Pages are only fetched from public addresses, as their title is shown to the user.
+#[derive(Debug)]
+pub struct HttpPageFetcher {
+    client: reqwest::Client,
+    max_bytes: usize,
+}
+
+impl HttpPageFetcher {
+    pub fn new(timeout: Duration, max_bytes: usize) -> Self {
+        let client = super::net::guarded_client(5)
+            .timeout(timeout)
+            .user_agent("bckt.xyz (+https://bckt.xyz/code)")
+            .build()
+            .expect("build page fetcher http client");
+        Self { client, max_bytes }
+    }
+}
+
+impl Default for HttpPageFetcher {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(3), 64 * 1024)
+    }
+}
+
+#[async_trait]
+impl PageFetcher for HttpPageFetcher {
+    async fn fetch(&self, url: &Url) -> Option<PageMeta> {
+        if !super::net::is_public_url(url) {
+            tracing::debug!("fetch page meta of {}: not a public url", url);
+            return None;
+        }
+        let response = match self
+            .client
+            .get(url.clone())
+            .header("Accept", "text/html")
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                tracing::debug!("fetch page meta of {}: status {}", url, response.status());
+                return None;
+            }
+            Err(e) => {
+                tracing::debug!("fetch page meta of {}: {}", url, e);
+                return None;
+            }
+        };
+        read_page_meta(response, self.max_bytes).await
+    }
+}
+
+/// Read the metadata from the first `max_bytes` of an html response.
+async fn read_page_meta(mut response: reqwest::Response, max_bytes: usize) -> Option<PageMeta> {
+    let url = response.url().clone();
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_ascii_lowercase().contains("html"))
+        .unwrap_or_default();
+    if !is_html {
+        return None;
+    }
+
+    // only read the head of the page, as that is where the metadata lives
+    let mut body = Vec::new();
+    while body.len() < max_bytes {
+        match response.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            Ok(None) => break,
+            Err(e) => {
+                tracing::debug!("read page meta of {}: {}", url, e);
+                break;
+            }
+        }
+    }
+    body.truncate(max_bytes);
+
+    Some(parse_page_meta(&String::from_utf8_lossy(&body), &url))
+}
+
+/// Parse the title and favicon from (the start of) an html page.
+pub fn parse_page_meta(html: &str, base: &Url) -> PageMeta {
+    // ascii lowercasing keeps byte offsets intact
+    let lower = html.to_ascii_lowercase();
+
+    let title = lower.find("<title").and_then(|start| {
+        let start = start + lower[start..].find('>')? + 1;
+        let end = start + lower[start..].find("</title")?;
+        let title = decode_entities(&html[start..end])
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if title.is_empty() {
+            None
+        } else {
+            Some(title.chars().take(256).collect())
+        }
+    });
+
+    let mut favicon = None;
+    let mut offset = 0;
+    while let Some(start) = lower[offset..].find("<link") {
+        let start = offset + start;
+        let end = match lower[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        offset = end;
+        let rel = attribute(&lower[start..end], "rel").unwrap_or_default();
+        if !rel.split_whitespace().any(|rel| rel == "icon") {
+            continue;
+        }
+        if let Some((href_start, href_end)) = attribute_range(&lower[start..end], "href") {
+            let href = decode_entities(&html[start + href_start..start + href_end]);
+            favicon = base.join(href.trim()).ok();
+            break;
+        }
+    }
+    let favicon = favicon
+        .or_else(|| base.join("/favicon.ico").ok())
+        .filter(|url| url.scheme() == "https" || url.scheme() == "http")
+        .map(|url| url.to_string());
+
+    PageMeta { title, favicon }
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    attribute_range(tag, name).map(|(start, end)| &tag[start..end])
+}
+
+/// Find the byte range of the value of an attribute within a (lowercased) tag.
+fn attribute_range(tag: &str, name: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    while let Some(index) = tag[offset..].find(name) {
+        let index = offset + index;
+        offset = index + name.len();
+        let preceded_by_space = tag[..index]
+            .chars()
+            .last()
+            .map(char::is_whitespace)
+            .unwrap_or_default();
+        let rest = tag[offset..].trim_start();
+        if !preceded_by_space || !rest.starts_with('=') {
+            continue;
+        }
+        let value = rest[1..].trim_start();
+        let value_start = tag.len() - value.len();
+        return match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let end = value[1..].find(quote)?;
+                Some((value_start + 1, value_start + 1 + end))
+            }
+            Some(_) => {
+                let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                Some((value_start, value_start + end))
+            }
+            None => None,
+        };
+    }
+    None
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn test_parse_page_meta() {
+        let base = Url::parse("https://example.com/a/b").unwrap();
+        let meta = parse_page_meta(
+            r#"<html><head>
+                <TITLE lang="en">
+                    Foo &amp; Bar
+                </TITLE>
+                <link rel="stylesheet" href="/style.css">
+                <link rel="shortcut icon" href='icons/fav.png'>
+            </head></html>"#,
+            &base,
+        );
+        assert_eq!(meta.title.as_deref(), Some("Foo & Bar"));
+        assert_eq!(
+            meta.favicon.as_deref(),
+            Some("https://example.com/a/icons/fav.png")
+        );
+    }
+
+    #[test]
+    fn test_parse_page_meta_defaults() {
+        let base = Url::parse("https://example.com/a/b").unwrap();
+        let meta = parse_page_meta("<html><head><title></title></head></html>", &base);
+        assert_eq!(meta.title, None);
+        assert_eq!(
+            meta.favicon.as_deref(),
+            Some("https://example.com/favicon.ico")
+        );
+    }
+
+    async fn serve_page(listener: TcpListener) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let _ = stream.read(&mut buf).await.unwrap();
+        let body = format!(
+            "<html><head><title>local</title></head><body>{}</body></html>",
+            "x".repeat(64 * 1024)
+        );
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+    }
+
+    #[tokio::test]
+    async fn test_http_page_fetcher_refuses_private_addresses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve_page(listener));
+
+        let fetcher = HttpPageFetcher::default();
+        for url in [
+            format!("http://127.0.0.1:{port}/page"),
+            format!("http://localhost:{port}/page"),
+            "http://169.254.169.254/latest/meta-data/".to_owned(),
+        ] {
+            assert_eq!(fetcher.fetch(&Url::parse(&url).unwrap()).await, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_page_meta_size_budget() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_page(listener));
+
+        // the guarded fetcher cannot reach the local server, so a plain client is used instead
+        let response = reqwest::get(format!("http://{addr}/page")).await.unwrap();
+        let meta = read_page_meta(response, 128).await.unwrap();
+        assert_eq!(meta.title.as_deref(), Some("local"));
+        assert_eq!(meta.favicon, Some(format!("http://{addr}/favicon.ico")));
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::http::HeaderMap;
+
+/// A token bucket rate limiter, keyed by client IP, where IPv6 clients
+/// are keyed by their /64 network, as that is what a single host usually gets.
+///
+/// Each client can make `burst` requests at once,
+/// after which its budget is refilled at `per_second` requests per second.
+/// At most `max_clients` are tracked, forgetting those seen least recently first.
+#[derive(Debug)]
+pub struct RateLimiter {
+    burst: f64,
+    per_second: f64,
+    max_clients: usize,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(burst: u32, per_second: f64) -> Self {
+        Self {
+            burst: burst as f64,
+            per_second,
+            max_clients: 100_000,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take one request from the budget of the client,
+    /// returning how long it has to wait in case its budget is exhausted.
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let client = bucket_key(client);
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= self.max_clients && !buckets.contains_key(&client) {
+            // buckets which are refilled are the same as no bucket at all
+            let (burst, per_second) = (self.burst, self.per_second);
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated_at).as_secs_f64() * per_second
+                    < burst
+            });
+            // forget an eighth of the clients at once when all are still limited,
+            // such that this pass over all buckets is spread over the clients that follow
+            let target = self.max_clients - (self.max_clients / 8).max(1);
+            if buckets.len() > target {
+                let mut updates = buckets
+                    .values()
+                    .map(|bucket| bucket.updated_at)
+                    .collect::<Vec<_>>();
+                let (_, cutoff, _) = updates.select_nth_unstable(buckets.len() - target - 1);
+                let cutoff = *cutoff;
+                buckets.retain(|_, bucket| bucket.updated_at > cutoff);
+            }
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.burst,
+            updated_at: now,
+        });
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+        bucket.updated_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.per_second,
+            ))
+        }
+    }
+}
+
+/// The key of the bucket of a client, being the /64 network of IPv6 addresses.
+fn bucket_key(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => ip,
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => IpAddr::V4(ip),
+            None => {
+                let mut segments = ip.segments();
+                segments[4..].fill(0);
+                IpAddr::V6(Ipv6Addr::from(segments))
+            }
+        },
+    }
+}
+
+/// Resolve the IP of the client, trusting the last `trusted_proxy_hops` entries
+/// of the `X-Forwarded-For` header, as those are added by our own proxies.
+/// Without trusted proxies the address of the connected peer is used.
+pub fn client_ip(
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+    trusted_proxy_hops: usize,
+) -> Option<IpAddr> {
+    if trusted_proxy_hops == 0 {
+        return peer.map(|peer| peer.ip());
+    }
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect();
+    if forwarded.is_empty() {
+        return peer.map(|peer| peer.ip());
+    }
+    let index = forwarded.len().saturating_sub(trusted_proxy_hops);
+    forwarded[index]
+        .parse::<IpAddr>()
+        .or_else(|_| forwarded[index].parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_burst_and_refill() {
+        let limiter = RateLimiter::new(2, 0.5);
+        let client = "10.0.0.1".parse().unwrap();
+        let now = Instant::now();
+        assert!(limiter.check_at(client, now).is_ok());
+        assert!(limiter.check_at(client, now).is_ok());
+        let retry_after = limiter.check_at(client, now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(2));
+        // other clients have their own budget
+        assert!(limiter.check_at("10.0.0.2".parse().unwrap(), now).is_ok());
+        assert!(limiter
+            .check_at(client, now + Duration::from_secs(2))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_rate_limiter_ipv6_network() {
+        let limiter = RateLimiter::new(1, 0.5);
+        let now = Instant::now();
+        assert!(limiter
+            .check_at("2001:db8:1:2::1".parse().unwrap(), now)
+            .is_ok());
+        // the same /64 network shares its budget
+        assert!(limiter
+            .check_at("2001:db8:1:2:ffff::1".parse().unwrap(), now)
+            .is_err());
+        assert!(limiter
+            .check_at("2001:db8:1:3::1".parse().unwrap(), now)
+            .is_ok());
+        // mapped IPv4 addresses are the same client as the IPv4 address
+        assert!(limiter.check_at("10.0.0.1".parse().unwrap(), now).is_ok());
+        assert!(limiter
+            .check_at("::ffff:10.0.0.1".parse().unwrap(), now)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_bounded() {
+        let mut limiter = RateLimiter::new(1, 0.001);
+        limiter.max_clients = 16;
+        let now = Instant::now();
+        for client in 0..16u8 {
+            let at = now + Duration::from_millis(client.into());
+            assert!(limiter.check_at([10, 0, 0, client].into(), at).is_ok());
+        }
+        let at = now + Duration::from_millis(16);
+        assert!(limiter.check_at([10, 0, 1, 0].into(), at).is_ok());
+        // the clients seen least recently are forgotten first, an eighth at once
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 15);
+        assert!(limiter.check_at([10, 0, 0, 0].into(), at).is_ok());
+        assert!(limiter.check_at([10, 0, 0, 15].into(), at).is_err());
+    }
+
+    #[test]
+    fn test_client_ip_peer() {
+        let headers = HeaderMap::new();
+        let peer = "192.168.1.5:1234".parse().ok();
+        assert_eq!(
+            client_ip(&headers, peer, 0),
+            Some("192.168.1.5".parse().unwrap())
+        );
+        assert_eq!(
+            client_ip(&headers, peer, 1),
+            Some("192.168.1.5".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_client_ip_unknown() {
+        let headers = HeaderMap::new();
+        assert_eq!(client_ip(&headers, None, 0), None);
+        assert_eq!(client_ip(&headers, None, 1), None);
+    }
+
+    #[test]
+    fn test_client_ip_forwarded() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "1.1.1.1, 2.2.2.2, 3.3.3.3".parse().unwrap(),
+        );
+        let peer = "192.168.1.5:1234".parse().ok();
+        // spoofed entries in front of the trusted ones are ignored
+        assert_eq!(
+            client_ip(&headers, peer, 1),
+            Some("3.3.3.3".parse().unwrap())
+        );
+        assert_eq!(
+            client_ip(&headers, peer, 2),
+            Some("2.2.2.2".parse().unwrap())
+        );
+        assert_eq!(
+            client_ip(&headers, peer, 5),
+            Some("1.1.1.1".parse().unwrap())
+        );
+        // without trusted proxies the header is ignored
+        assert_eq!(
+            client_ip(&headers, peer, 0),
+            Some("192.168.1.5".parse().unwrap())
+        );
+    }
+}
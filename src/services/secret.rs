@@ -0,0 +1,119 @@
+use std::{sync::Arc, time::Duration};
+
+use askama::Template;
+
+use super::{BlobStore, Mailer, Storage};
+
+#[derive(Template)]
+#[template(path = "../templates/email/secret_read.html")]
+struct ReadReceiptHtmlTemplate<'a> {
+    url: &'a str,
+    viewed_at: &'a str,
+    viewed_by: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "../templates/email/secret_read.txt")]
+struct ReadReceiptTextTemplate<'a> {
+    url: &'a str,
+    viewed_at: &'a str,
+    viewed_by: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "../templates/email/secret_login.html")]
+struct RecipientLoginHtmlTemplate<'a> {
+    url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "../templates/email/secret_login.txt")]
+struct RecipientLoginTextTemplate<'a> {
+    url: &'a str,
+}
+
+/// Send the recipient of a secret the link to log in with, in order to read it.
+///
+/// This happens in the background, such that the response does not reveal
+/// whether or not the address is the one the secret is bound to.
+pub fn spawn_recipient_login(mailer: Mailer, email: String, url: String) {
+    tokio::spawn(async move {
+        let (html, text) = match (
+            RecipientLoginHtmlTemplate { url: &url }.render(),
+            RecipientLoginTextTemplate { url: &url }.render(),
+        ) {
+            (Ok(html), Ok(text)) => (html, text),
+            _ => {
+                tracing::error!("Failed to render recipient login");
+                return;
+            }
+        };
+        if let Err(err) = mailer
+            .send_html(&email, "Log in to read your secret", &html, &text)
+            .await
+        {
+            tracing::error!("Failed to send recipient login: {}", err);
+        }
+    });
+}
+
+/// Let the creator of a secret know that it was read, in the background,
+/// such that revealing the secret does not wait for the mail to be sent.
+pub fn spawn_read_receipt(mailer: Mailer, email: String, url: String, viewed_by: String) {
+    tokio::spawn(async move {
+        let viewed_at = chrono::Utc::now().format("%Y-%m-%d %H:%M UTC").to_string();
+        let (html, text) = match (
+            ReadReceiptHtmlTemplate {
+                url: &url,
+                viewed_at: &viewed_at,
+                viewed_by: &viewed_by,
+            }
+            .render(),
+            ReadReceiptTextTemplate {
+                url: &url,
+                viewed_at: &viewed_at,
+                viewed_by: &viewed_by,
+            }
+            .render(),
+        ) {
+            (Ok(html), Ok(text)) => (html, text),
+            _ => {
+                tracing::error!("Failed to render read receipt for {}", url);
+                return;
+            }
+        };
+        if let Err(err) = mailer
+            .send_html(&email, "Your secret was read", &html, &text)
+            .await
+        {
+            tracing::error!("Failed to send read receipt for {}: {}", url, err);
+        }
+    });
+}
+
+/// Purge the ciphertext of expired secrets every `interval`, as well as the files
+/// which can no longer be downloaded, such that secrets never stay around longer
+/// than they were meant to.
+pub fn spawn_purge(storage: Storage, blobs: Arc<dyn BlobStore>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match storage.purge_expired_secrets().await {
+                Ok(0) => (),
+                Ok(purged) => tracing::info!("Purged {} expired secrets", purged),
+                Err(err) => tracing::error!("Failed to purge expired secrets: {}", err),
+            }
+            for key in storage.get_unused_blobs().await {
+                // the blob is only forgotten once it is gone, such that failures are retried
+                if let Err(err) = blobs.delete(&key).await {
+                    tracing::error!("Failed to delete blob {}: {}", key, err);
+                    continue;
+                }
+                if let Err(err) = storage.delete_blob(&key).await {
+                    tracing::error!("Failed to forget blob {}: {}", key, err);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{header::LOCATION, Url};
+
+/// Domains of third-party link shorteners, which can hide the actual destination.
+const KNOWN_SHORTENERS: &[&str] = &[
+    "bit.ly",
+    "bitly.com",
+    "j.mp",
+    "tinyurl.com",
+    "t.co",
+    "goo.gl",
+    "ow.ly",
+    "is.gd",
+    "v.gd",
+    "buff.ly",
+    "rebrand.ly",
+    "cutt.ly",
+    "shorturl.at",
+    "tiny.cc",
+    "rb.gy",
+    "t.ly",
+    "bl.ink",
+    "lnkd.in",
+    "s.id",
+    "shorte.st",
+    "adf.ly",
+    "qr.ae",
+    "trib.al",
+    "dlvr.it",
+    "soo.gd",
+    "clck.ru",
+];
+
+/// Whether or not the (punycode) host belongs to a known third-party link shortener.
+pub fn is_known_shortener(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    KNOWN_SHORTENERS.contains(&host)
+}
+
+/// Resolves a link of a third-party shortener to its final destination.
+#[async_trait]
+pub trait ShortenerResolver: std::fmt::Debug + Send + Sync {
+    async fn resolve(&self, url: &Url) -> Option<Url>;
+}
+
+/// Resolves shortened links by following their redirects over http(s),
+/// for as long as they point to a known shortener, up to `max_hops` redirects.
+///
+/// Every hop is only requested if it is a public address.
+#[derive(Debug)]
+pub struct HttpShortenerResolver {
+    client: reqwest::Client,
+    max_hops: usize,
+}
+
+impl HttpShortenerResolver {
+    pub fn new(timeout: Duration, max_hops: usize) -> Self {
+        let client = super::net::guarded_client(0)
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent("bckt.xyz (+https://bckt.xyz/code)")
+            .build()
+            .expect("build shortener resolver http client");
+        Self { client, max_hops }
+    }
+}
+
+impl Default for HttpShortenerResolver {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(3), 5)
+    }
+}
+
+#[async_trait]
+impl ShortenerResolver for HttpShortenerResolver {
+    async fn resolve(&self, url: &Url) -> Option<Url> {
+        let mut url = url.clone();
+        for _ in 0..self.max_hops {
+            if !super::net::is_public_url(&url) {
+                tracing::debug!("resolve shortened link {}: not a public url", url);
+                return None;
+            }
+            // not every shortener answers HEAD requests, so a GET is used instead
+            let response = match self.client.get(url.clone()).send().await {
+                Ok(response) => response,
+                Err(e) => {
+                    tracing::debug!("resolve shortened link {}: {}", url, e);
+                    return None;
+                }
+            };
+            if !response.status().is_redirection() {
+                return Some(url);
+            }
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|location| url.join(location).ok())?;
+            url = location;
+            if !url.host_str().map(is_known_shortener).unwrap_or_default() {
+                return Some(url);
+            }
+        }
+        tracing::debug!("resolve shortened link {}: too many redirects", url);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn test_is_known_shortener() {
+        assert!(is_known_shortener("bit.ly"));
+        assert!(is_known_shortener("www.TinyURL.com"));
+        assert!(is_known_shortener("t.co."));
+        assert!(!is_known_shortener("example.com"));
+        assert!(!is_known_shortener("notbit.ly"));
+    }
+
+    #[tokio::test]
+    async fn test_http_shortener_resolver_refuses_private_addresses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            let response = "HTTP/1.1 301 Moved Permanently\r\nLocation: https://example.com/final\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+
+        let resolver = HttpShortenerResolver::new(Duration::from_secs(1), 5);
+        for url in [
+            format!("http://{addr}/abc"),
+            format!("http://localhost:{}/abc", addr.port()),
+        ] {
+            assert_eq!(resolver.resolve(&Url::parse(&url).unwrap()).await, None);
+        }
+    }
+}
@@ -1,15 +1,118 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
 use sqlx::PgPool;
 
-use crate::data::Shortlink;
+use super::{user_agent::Visitor, webhook::WebhookLog, PageMeta, TtlCache};
+
+use crate::data::{
+    Collection, CollectionItem, Domain, LinkTransfer, Secret, SecretRequest, SecretReveal,
+    Shortlink, Webhook, WebhookDelivery, Workspace, WorkspaceMember, WorkspaceRole, MAX_TTL_HOURS,
+};
+
+/// SQL condition on a shortlink `l` which holds when the user, given as an SQL expression,
+/// is allowed to edit it: the owner of a personal shortlink,
+/// or an owner or editor of the workspace it belongs to.
+fn can_edit_link(email: &str) -> String {
+    format!(
+        "((l.workspace_id IS NULL AND l.owner_email = {email})
+            OR EXISTS (
+                SELECT 1 FROM bckt_workspace_members m
+                WHERE m.workspace_id = l.workspace_id
+                    AND m.member_email = {email}
+                    AND m.role IN ('owner', 'editor')
+            ))"
+    )
+}
+
+/// The maximum amount of referring hosts tracked per shortlink.
+const MAX_REFERRERS_PER_LINK: i64 = 100;
+
+/// A shortlink is identified by its custom domain, if any, and its hash.
+type ShortlinkKey = (Option<String>, String);
 
 #[derive(Debug, Clone)]
 pub struct Storage {
     pool: PgPool,
+    shortlink_cache: Arc<TtlCache<ShortlinkKey, Shortlink>>,
+    domain_cache: Arc<TtlCache<String, Domain>>,
+    webhook_cache: Arc<TtlCache<ShortlinkKey, Vec<Webhook>>>,
+    secret_counters: Arc<SecretCounters>,
+}
+
+/// Counts what happened to secrets since this process started, which is not persisted
+/// and thus starts from zero again after every restart.
+#[derive(Debug, Default)]
+pub struct SecretCounters {
+    read: AtomicU64,
+    purged: AtomicU64,
+}
+
+impl SecretCounters {
+    pub fn read(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    pub fn purged(&self) -> u64 {
+        self.purged.load(Ordering::Relaxed)
+    }
 }
 
 impl Storage {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            shortlink_cache: Arc::new(TtlCache::new(
+                10_000,
+                Duration::from_secs(60),
+                Duration::from_secs(10),
+            )),
+            domain_cache: Arc::new(TtlCache::new(
+                1_000,
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+            )),
+            webhook_cache: Arc::new(TtlCache::new(
+                10_000,
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+            )),
+            secret_counters: Arc::new(SecretCounters::default()),
+        }
+    }
+
+    /// Cache in front of [`Storage::get_shortlink`].
+    ///
+    /// Clicks on a cached shortlink are counted in the background,
+    /// and the webhooks to notify of them are cached next to it.
+    pub fn shortlink_cache(&self) -> &TtlCache<ShortlinkKey, Shortlink> {
+        &self.shortlink_cache
+    }
+
+    /// Cache in front of [`Storage::get_domain`].
+    pub fn domain_cache(&self) -> &TtlCache<String, Domain> {
+        &self.domain_cache
+    }
+
+    /// Cache in front of [`Storage::get_webhooks_for_shortlink`].
+    pub fn webhook_cache(&self) -> &TtlCache<ShortlinkKey, Vec<Webhook>> {
+        &self.webhook_cache
+    }
+
+    pub fn secret_counters(&self) -> &SecretCounters {
+        &self.secret_counters
+    }
+
+    fn invalidate_shortlink(&self, domain: Option<&str>, id: &str) {
+        let key = (domain.map(str::to_owned), id.to_owned());
+        self.shortlink_cache.invalidate(&key);
+        self.webhook_cache.invalidate(&key);
     }
 
     pub async fn is_domain_blocked(&self, _domain: &str) -> bool {
@@ -18,45 +121,1661 @@ impl Storage {
 
     pub async fn add_shortlink(&self, shortlink: &Shortlink) -> Result<(), String> {
         sqlx::query(
-            "INSERT INTO bckt_links (link_hash, link_long, owner_email) VALUES ($1, $2, $3)",
+            "INSERT INTO bckt_links (link_hash, link_long, link_canonical, owner_email, workspace_id, domain) VALUES ($1, $2, $3, $4, $5, $6)",
         )
         .bind(shortlink.link_hash())
         .bind(shortlink.link_long())
+        .bind(shortlink.link_canonical())
         .bind(shortlink.owner_email())
+        .bind(shortlink.workspace_id())
+        .bind(shortlink.domain())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        // the hash might have been cached as unknown
+        self.invalidate_shortlink(shortlink.domain(), shortlink.link_hash());
+        Ok(())
+    }
+
+    /// Get a shortlink within the namespace of a custom domain,
+    /// or within the default namespace if no domain is given.
+    pub async fn get_shortlink(&self, domain: Option<&str>, id: &str) -> Option<Shortlink> {
+        let key = (domain.map(str::to_owned), id.to_owned());
+        if let Some(shortlink) = self.shortlink_cache.get(&key) {
+            return shortlink;
+        }
+        match sqlx::query_as::<_, Shortlink>(
+            "SELECT * FROM bckt_links WHERE link_hash = $1 AND domain IS NOT DISTINCT FROM $2",
+        )
+        .bind(id)
+        .bind(domain)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(shortlink) => {
+                self.shortlink_cache.insert(key, shortlink.clone());
+                shortlink
+            }
+            Err(err) => {
+                tracing::error!("Failed to get shortlink {}: {}", id, err);
+                None
+            }
+        }
+    }
+
+    /// Count a visit of a shortlink, where only humans count as a click,
+    /// and bots and preview fetchers are counted separately.
+    /// Clicks are also counted per day and per referring host, as to summarize them later,
+    /// where new referring hosts are no longer tracked once a link has too many of them.
+    pub async fn add_shortlink_click(
+        &self,
+        shortlink: &Shortlink,
+        visitor: Visitor,
+        referrer_host: Option<&str>,
+    ) -> Result<(), String> {
+        let column = match visitor {
+            Visitor::Human => {
+                return sqlx::query(
+                    "WITH link AS (
+                        UPDATE bckt_links SET clicks = clicks + 1
+                            WHERE link_hash = $1 AND domain IS NOT DISTINCT FROM $2
+                            RETURNING id
+                    ), daily AS (
+                        INSERT INTO bckt_link_clicks_daily (link_id, day, clicks)
+                            SELECT id, CURRENT_DATE, 1 FROM link
+                            ON CONFLICT (link_id, day)
+                            DO UPDATE SET clicks = bckt_link_clicks_daily.clicks + 1
+                    )
+                    INSERT INTO bckt_link_referrers (link_id, referrer_host, clicks)
+                        SELECT id, $3::VARCHAR, 1 FROM link WHERE $3::VARCHAR IS NOT NULL AND (
+                            EXISTS (
+                                SELECT 1 FROM bckt_link_referrers r
+                                WHERE r.link_id = link.id AND r.referrer_host = $3::VARCHAR
+                            )
+                            OR (
+                                SELECT COUNT(*) FROM bckt_link_referrers r WHERE r.link_id = link.id
+                            ) < $4
+                        )
+                        ON CONFLICT (link_id, referrer_host)
+                        DO UPDATE SET clicks = bckt_link_referrers.clicks + 1",
+                )
+                .bind(shortlink.link_hash())
+                .bind(shortlink.domain())
+                .bind(referrer_host)
+                .bind(MAX_REFERRERS_PER_LINK)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| e.to_string())
+                .map(|_| ());
+            }
+            Visitor::Bot => "clicks_bot",
+            Visitor::Preview => "clicks_preview",
+        };
+        sqlx::query(&format!(
+            "UPDATE bckt_links SET {column} = {column} + 1 WHERE link_hash = $1 AND domain IS NOT DISTINCT FROM $2",
+        ))
+        .bind(shortlink.link_hash())
+        .bind(shortlink.domain())
         .execute(&self.pool)
         .await
         .map_err(|e| e.to_string())
         .map(|_| ())
     }
 
-    pub async fn get_shortlink(&self, id: &str) -> Option<Shortlink> {
-        sqlx::query_as::<_, Shortlink>("SELECT * FROM bckt_links WHERE link_hash = $1")
-            .bind(id)
+    /// Set the title and note of a shortlink the user is allowed to edit.
+    pub async fn set_shortlink_note(
+        &self,
+        id: &str,
+        domain: Option<&str>,
+        email_hash: &str,
+        title: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<(), String> {
+        let len = sqlx::query(&format!(
+            "UPDATE bckt_links l SET title = $4, note = $5
+                WHERE l.link_hash = $2 AND l.domain IS NOT DISTINCT FROM $3 AND {}",
+            can_edit_link("$1")
+        ))
+        .bind(email_hash)
+        .bind(id)
+        .bind(domain)
+        .bind(title)
+        .bind(note)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err(format!(
+                "no shortlink '{id}' exists that you are allowed to edit"
+            ))
+        } else {
+            self.invalidate_shortlink(domain, id);
+            Ok(())
+        }
+    }
+
+    /// Set the social preview metadata of a shortlink the user is allowed to edit.
+    pub async fn set_shortlink_preview(
+        &self,
+        id: &str,
+        domain: Option<&str>,
+        email_hash: &str,
+        title: Option<&str>,
+        description: Option<&str>,
+        image: Option<&str>,
+    ) -> Result<(), String> {
+        let len = sqlx::query(&format!(
+            "UPDATE bckt_links l
+                SET preview_title = $4, preview_description = $5, preview_image = $6
+                WHERE l.link_hash = $2 AND l.domain IS NOT DISTINCT FROM $3 AND {}",
+            can_edit_link("$1")
+        ))
+        .bind(email_hash)
+        .bind(id)
+        .bind(domain)
+        .bind(title)
+        .bind(description)
+        .bind(image)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err(format!(
+                "no shortlink '{id}' exists that you are allowed to edit"
+            ))
+        } else {
+            self.invalidate_shortlink(domain, id);
+            Ok(())
+        }
+    }
+
+    /// Store the fetched metadata of the destination page of a shortlink,
+    /// unless the owner has overridden it.
+    pub async fn set_shortlink_page_meta(
+        &self,
+        shortlink: &Shortlink,
+        meta: &PageMeta,
+    ) -> Result<(), String> {
+        sqlx::query(
+            "UPDATE bckt_links SET page_title = $3, page_favicon = $4
+                WHERE link_hash = $1 AND domain IS NOT DISTINCT FROM $2 AND NOT page_meta_override",
+        )
+        .bind(shortlink.link_hash())
+        .bind(shortlink.domain())
+        .bind(meta.title.as_deref())
+        .bind(meta.favicon.as_deref())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        self.invalidate_shortlink(shortlink.domain(), shortlink.link_hash());
+        Ok(())
+    }
+
+    /// Override the metadata of the destination page of a shortlink the user is allowed to edit.
+    /// No metadata at all clears the override, returning the shortlink when it was cleared.
+    pub async fn override_shortlink_page_meta(
+        &self,
+        id: &str,
+        domain: Option<&str>,
+        email_hash: &str,
+        meta: &PageMeta,
+    ) -> Result<Option<Shortlink>, String> {
+        let clear = meta.title.is_none() && meta.favicon.is_none();
+        let shortlink = sqlx::query_as::<_, Shortlink>(&format!(
+            "UPDATE bckt_links l SET page_title = $4, page_favicon = $5, page_meta_override = $6
+                WHERE l.link_hash = $2 AND l.domain IS NOT DISTINCT FROM $3 AND {}
+                RETURNING *",
+            can_edit_link("$1")
+        ))
+        .bind(email_hash)
+        .bind(id)
+        .bind(domain)
+        .bind(meta.title.as_deref())
+        .bind(meta.favicon.as_deref())
+        .bind(!clear)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("no shortlink '{id}' exists that you are allowed to edit"))?;
+        self.invalidate_shortlink(domain, id);
+        Ok(if clear { Some(shortlink) } else { None })
+    }
+
+    /// Get the personal shortlinks of an owner,
+    /// shortlinks which belong to a workspace are not included.
+    pub async fn get_shortlinks_for_owner(&self, owner_email: &str) -> Vec<Shortlink> {
+        sqlx::query_as::<_, Shortlink>(
+            "SELECT * FROM bckt_links WHERE owner_email = $1 AND workspace_id IS NULL",
+        )
+        .bind(owner_email)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    pub async fn get_shortlinks_for_workspace(&self, workspace_id: i32) -> Vec<Shortlink> {
+        sqlx::query_as::<_, Shortlink>("SELECT * FROM bckt_links WHERE workspace_id = $1")
+            .bind(workspace_id)
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Delete a shortlink the user is allowed to edit.
+    pub async fn delete_shortlink(
+        &self,
+        id: &str,
+        domain: Option<&str>,
+        email_hash: &str,
+    ) -> Result<(), String> {
+        let len = sqlx::query(&format!(
+            "DELETE FROM bckt_links l
+                WHERE l.link_hash = $2 AND l.domain IS NOT DISTINCT FROM $3 AND {}",
+            can_edit_link("$1")
+        ))
+        .bind(email_hash)
+        .bind(id)
+        .bind(domain)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err(format!(
+                "no shortlink '{id}' exists that you are allowed to delete"
+            ))
+        } else {
+            self.invalidate_shortlink(domain, id);
+            Ok(())
+        }
+    }
+
+    /// Get a verified custom domain, as only those serve shortlinks.
+    pub async fn get_domain(&self, host: &str) -> Option<Domain> {
+        if let Some(domain) = self.domain_cache.get(&host.to_owned()) {
+            return domain;
+        }
+        match sqlx::query_as::<_, Domain>(
+            "SELECT * FROM bckt_domains WHERE host = $1 AND verified_at IS NOT NULL",
+        )
+        .bind(host)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(domain) => {
+                self.domain_cache.insert(host.to_owned(), domain.clone());
+                domain
+            }
+            Err(err) => {
+                tracing::error!("Failed to get domain {}: {}", host, err);
+                None
+            }
+        }
+    }
+
+    pub async fn get_domains_for_owner(&self, owner_email: &str) -> Vec<Domain> {
+        sqlx::query_as::<_, Domain>(
+            "SELECT * FROM bckt_domains WHERE owner_email = $1 ORDER BY host",
+        )
+        .bind(owner_email)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    pub async fn get_domains_for_workspace(&self, workspace_id: i32) -> Vec<Domain> {
+        sqlx::query_as::<_, Domain>(
+            "SELECT * FROM bckt_domains WHERE workspace_id = $1 ORDER BY host",
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Claim a custom domain for either a single owner or a workspace,
+    /// which has to be verified using the generated token before it is served.
+    ///
+    /// Claims which are not verified yet can be taken over,
+    /// such that nobody can hold on to a domain they do not control.
+    pub async fn add_domain(
+        &self,
+        host: &str,
+        owner_email: Option<&str>,
+        workspace_id: Option<i32>,
+    ) -> Result<(), String> {
+        let len = sqlx::query(
+            "INSERT INTO bckt_domains (host, owner_email, workspace_id, verification_token)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (host) DO UPDATE SET
+                    owner_email = EXCLUDED.owner_email,
+                    workspace_id = EXCLUDED.workspace_id,
+                    verification_token = EXCLUDED.verification_token
+                WHERE bckt_domains.verified_at IS NULL",
+        )
+        .bind(host)
+        .bind(owner_email)
+        .bind(workspace_id)
+        .bind(nanoid::nanoid!(32))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+        if len == 0 {
+            Err(format!("the domain '{host}' is already claimed"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get a custom domain, verified or not, which the user is allowed to manage.
+    pub async fn get_managed_domain(&self, host: &str, email_hash: &str) -> Option<Domain> {
+        sqlx::query_as::<_, Domain>(
+            "SELECT d.* FROM bckt_domains d WHERE d.host = $1 AND (
+                d.owner_email = $2
+                OR EXISTS (
+                    SELECT 1 FROM bckt_workspace_members m
+                    WHERE m.workspace_id = d.workspace_id
+                        AND m.member_email = $2
+                        AND m.role = 'owner'
+                )
+            )",
+        )
+        .bind(host)
+        .bind(email_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Mark the claim on a custom domain as verified, such that it starts serving shortlinks.
+    pub async fn verify_domain(&self, domain: &Domain) -> Result<(), String> {
+        let len = sqlx::query(
+            "UPDATE bckt_domains SET verified_at = NOW()
+                WHERE host = $1 AND verification_token = $2 AND verified_at IS NULL",
+        )
+        .bind(domain.host())
+        .bind(domain.verification_token())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+        if len == 0 {
+            Err(format!(
+                "the claim on the domain '{}' changed in the meantime",
+                domain.host()
+            ))
+        } else {
+            self.domain_cache.invalidate(&domain.host().to_owned());
+            Ok(())
+        }
+    }
+
+    /// Delete a custom domain, together with all shortlinks in its namespace,
+    /// which is only allowed for its owner or the owners of its workspace.
+    pub async fn delete_domain(&self, host: &str, email_hash: &str) -> Result<(), String> {
+        let len = sqlx::query(
+            "DELETE FROM bckt_domains d WHERE d.host = $1 AND (
+                d.owner_email = $2
+                OR EXISTS (
+                    SELECT 1 FROM bckt_workspace_members m
+                    WHERE m.workspace_id = d.workspace_id
+                        AND m.member_email = $2
+                        AND m.role = 'owner'
+                )
+            )",
+        )
+        .bind(host)
+        .bind(email_hash)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err(format!(
+                "no domain '{host}' exists that you are allowed to delete"
+            ))
+        } else {
+            // the shortlinks in the namespace of the domain are deleted together with it
+            self.domain_cache.invalidate(&host.to_owned());
+            self.shortlink_cache
+                .invalidate_where(|(domain, _)| domain.as_deref() == Some(host));
+            Ok(())
+        }
+    }
+
+    /// Nominate a recipient for a personal shortlink,
+    /// replacing any transfer that was still pending for that shortlink.
+    pub async fn add_link_transfer(
+        &self,
+        id: &str,
+        sender_email: &str,
+        sender_display: &str,
+        recipient_email: &str,
+        recipient_display: &str,
+    ) -> Result<(), String> {
+        let len = sqlx::query(
+            "INSERT INTO bckt_link_transfers (link_id, sender_email, sender_display, recipient_email, recipient_display)
+                SELECT id, owner_email, $3, $4, $5 FROM bckt_links
+                WHERE link_hash = $1 AND owner_email = $2 AND workspace_id IS NULL AND domain IS NULL
+                ON CONFLICT (link_id) DO UPDATE SET
+                    sender_display = EXCLUDED.sender_display,
+                    recipient_email = EXCLUDED.recipient_email,
+                    recipient_display = EXCLUDED.recipient_display",
+        )
+        .bind(id)
+        .bind(sender_email)
+        .bind(sender_display)
+        .bind(recipient_email)
+        .bind(recipient_display)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err(format!(
+                "no personal shortlink '{id}' exists for current owner"
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn get_outgoing_link_transfers(&self, sender_email: &str) -> Vec<LinkTransfer> {
+        sqlx::query_as::<_, LinkTransfer>(
+            "SELECT t.id, l.link_hash, COALESCE(l.link_canonical, l.link_long) AS link_long, t.sender_display, t.recipient_display
+                FROM bckt_link_transfers t JOIN bckt_links l ON l.id = t.link_id
+                WHERE t.sender_email = $1 AND l.owner_email = $1",
+        )
+        .bind(sender_email)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    pub async fn get_incoming_link_transfers(&self, recipient_email: &str) -> Vec<LinkTransfer> {
+        sqlx::query_as::<_, LinkTransfer>(
+            "SELECT t.id, l.link_hash, COALESCE(l.link_canonical, l.link_long) AS link_long, t.sender_display, t.recipient_display
+                FROM bckt_link_transfers t JOIN bckt_links l ON l.id = t.link_id
+                WHERE t.recipient_email = $1 AND l.owner_email = t.sender_email",
+        )
+        .bind(recipient_email)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Cancel a pending transfer, either by its sender or declined by its recipient.
+    pub async fn cancel_link_transfer(&self, id: i32, email_hash: &str) -> Result<(), String> {
+        let len = sqlx::query(
+            "DELETE FROM bckt_link_transfers WHERE id = $1 AND (sender_email = $2 OR recipient_email = $2)",
+        )
+        .bind(id)
+        .bind(email_hash)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err("no such pending transfer exists".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Accept a pending transfer, making the recipient the new owner of the shortlink.
+    pub async fn accept_link_transfer(&self, id: i32, recipient_email: &str) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        let (link_id, sender_email): (i32, String) = sqlx::query_as(
+            "DELETE FROM bckt_link_transfers WHERE id = $1 AND recipient_email = $2 RETURNING link_id, sender_email",
+        )
+        .bind(id)
+        .bind(recipient_email)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "no such pending transfer exists".to_string())?;
+        // shortlinks on a custom domain of the sender would be deleted together with it
+        let (link_hash, domain): (String, Option<String>) = sqlx::query_as(
+            "UPDATE bckt_links SET owner_email = $3
+                WHERE id = $1 AND owner_email = $2 AND workspace_id IS NULL AND domain IS NULL
+                RETURNING link_hash, domain",
+        )
+        .bind(link_id)
+        .bind(&sender_email)
+        .bind(recipient_email)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "the shortlink is no longer owned by its sender".to_string())?;
+        // the collections of the sender can no longer list the shortlink
+        sqlx::query(
+            "DELETE FROM bckt_collection_items i USING bckt_collections c
+                WHERE i.collection_id = c.id AND i.link_id = $1 AND c.owner_email = $2",
+        )
+        .bind(link_id)
+        .bind(sender_email)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+        self.invalidate_shortlink(domain.as_deref(), &link_hash);
+        Ok(())
+    }
+
+    pub async fn add_collection(
+        &self,
+        slug: &str,
+        owner_email: &str,
+        title: &str,
+        description: &str,
+    ) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO bckt_collections (slug, owner_email, title, description) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(slug)
+        .bind(owner_email)
+        .bind(title)
+        .bind(description)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                format!("the collection '@{slug}' already exists")
+            }
+            e => e.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    pub async fn get_collection(&self, slug: &str) -> Option<Collection> {
+        sqlx::query_as::<_, Collection>("SELECT * FROM bckt_collections WHERE slug = $1")
+            .bind(slug)
             .fetch_one(&self.pool)
             .await
             .ok()
     }
 
-    pub async fn get_shortlinks_for_owner(&self, owner_email: &str) -> Vec<Shortlink> {
-        sqlx::query_as::<_, Shortlink>("SELECT * FROM bckt_links WHERE owner_email = $1")
+    pub async fn get_collections_for_owner(&self, owner_email: &str) -> Vec<Collection> {
+        sqlx::query_as::<_, Collection>(
+            "SELECT * FROM bckt_collections WHERE owner_email = $1 ORDER BY slug",
+        )
+        .bind(owner_email)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    pub async fn delete_collection(&self, id: i32, owner_email: &str) -> Result<(), String> {
+        let len = sqlx::query("DELETE FROM bckt_collections WHERE id = $1 AND owner_email = $2")
+            .bind(id)
             .bind(owner_email)
-            .fetch_all(&self.pool)
+            .execute(&self.pool)
             .await
-            .unwrap_or_default()
+            .map_err(|e| e.to_string())
+            .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err("no such collection exists for current owner".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the items of a collection, in order.
+    pub async fn get_collection_items(&self, collection_id: i32) -> Vec<CollectionItem> {
+        sqlx::query_as::<_, CollectionItem>(
+            "SELECT i.id, l.link_hash, l.domain, l.clicks, i.title, i.description
+                FROM bckt_collection_items i JOIN bckt_links l ON l.id = i.link_id
+                WHERE i.collection_id = $1
+                ORDER BY i.position, i.id",
+        )
+        .bind(collection_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Append one of the owner's personal shortlinks to the end of one of their collections.
+    pub async fn add_collection_item(
+        &self,
+        collection_id: i32,
+        owner_email: &str,
+        link_hash: &str,
+        domain: Option<&str>,
+        title: &str,
+        description: &str,
+    ) -> Result<(), String> {
+        let len = sqlx::query(
+            "INSERT INTO bckt_collection_items (collection_id, link_id, position, title, description)
+                SELECT c.id, l.id, COALESCE((
+                    SELECT MAX(i.position) + 1 FROM bckt_collection_items i WHERE i.collection_id = c.id
+                ), 0), $4, $5
+                FROM bckt_collections c JOIN bckt_links l ON l.owner_email = c.owner_email
+                WHERE c.id = $1 AND c.owner_email = $2 AND l.workspace_id IS NULL
+                    AND l.link_hash = $3 AND l.domain IS NOT DISTINCT FROM $6",
+        )
+        .bind(collection_id)
+        .bind(owner_email)
+        .bind(link_hash)
+        .bind(title)
+        .bind(description)
+        .bind(domain)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err(format!(
+                "no personal shortlink '{link_hash}' or collection exists for current owner"
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Swap the position of an item with its previous (up) or next (down) neighbour.
+    pub async fn move_collection_item(
+        &self,
+        item_id: i32,
+        owner_email: &str,
+        up: bool,
+    ) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        let items: Vec<(i32, i32)> = sqlx::query_as(
+            "SELECT i.id, i.position FROM bckt_collection_items i
+                JOIN bckt_collections c ON c.id = i.collection_id
+                WHERE c.owner_email = $2 AND i.collection_id = (
+                    SELECT collection_id FROM bckt_collection_items WHERE id = $1
+                )
+                ORDER BY i.position, i.id
+                FOR UPDATE OF i",
+        )
+        .bind(item_id)
+        .bind(owner_email)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        let index = items
+            .iter()
+            .position(|(id, _)| *id == item_id)
+            .ok_or_else(|| "no such collection item exists for current owner".to_string())?;
+        let other = match (up, index) {
+            (true, 0) => return Ok(()),
+            (true, index) => index - 1,
+            (false, index) if index + 1 >= items.len() => return Ok(()),
+            (false, index) => index + 1,
+        };
+        // positions are rewritten in full, as they are not guaranteed to be unique
+        for (position, (id, _)) in items.iter().enumerate() {
+            let position = if position == index {
+                other
+            } else if position == other {
+                index
+            } else {
+                position
+            };
+            sqlx::query("UPDATE bckt_collection_items SET position = $2 WHERE id = $1")
+                .bind(id)
+                .bind(position as i32)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn delete_collection_item(
+        &self,
+        item_id: i32,
+        owner_email: &str,
+    ) -> Result<(), String> {
+        let len = sqlx::query(
+            "DELETE FROM bckt_collection_items i USING bckt_collections c
+                WHERE i.id = $1 AND c.id = i.collection_id AND c.owner_email = $2",
+        )
+        .bind(item_id)
+        .bind(owner_email)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err("no such collection item exists for current owner".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Create a new workspace, with the given member as its (first) owner.
+    pub async fn add_workspace(
+        &self,
+        name: &str,
+        email_hash: &str,
+        email: &str,
+    ) -> Result<i32, String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        let (id,): (i32,) =
+            sqlx::query_as("INSERT INTO bckt_workspaces (name) VALUES ($1) RETURNING id")
+                .bind(name)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        sqlx::query(
+            "INSERT INTO bckt_workspace_members (workspace_id, member_email, member_display, role) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(id)
+        .bind(email_hash)
+        .bind(email)
+        .bind(WorkspaceRole::Owner.as_str())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(id)
+    }
+
+    /// Delete a workspace, together with its domains and the shortlinks served by those,
+    /// while its other shortlinks fall back to their creators.
+    pub async fn delete_workspace(&self, workspace_id: i32) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        let hosts = sqlx::query_scalar::<_, String>(
+            "DELETE FROM bckt_domains WHERE workspace_id = $1 RETURNING host",
+        )
+        .bind(workspace_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        let links = sqlx::query_as::<_, (Option<String>, String)>(
+            "UPDATE bckt_links SET workspace_id = NULL WHERE workspace_id = $1
+                RETURNING domain, link_hash",
+        )
+        .bind(workspace_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        sqlx::query("DELETE FROM bckt_workspaces WHERE id = $1")
+            .bind(workspace_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        for host in &hosts {
+            self.domain_cache.invalidate(host);
+        }
+        self.shortlink_cache
+            .invalidate_where(|(domain, _)| domain.as_ref().is_some_and(|d| hosts.contains(d)));
+        for (domain, hash) in links {
+            self.invalidate_shortlink(domain.as_deref(), &hash);
+        }
+        Ok(())
+    }
+
+    pub async fn get_workspaces_for_member(&self, email_hash: &str) -> Vec<Workspace> {
+        sqlx::query_as::<_, Workspace>(
+            "SELECT w.id, w.name, m.role FROM bckt_workspaces w
+                JOIN bckt_workspace_members m ON m.workspace_id = w.id
+                WHERE m.member_email = $1
+                ORDER BY w.name",
+        )
+        .bind(email_hash)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    pub async fn get_workspace_role(
+        &self,
+        workspace_id: i32,
+        email_hash: &str,
+    ) -> Option<WorkspaceRole> {
+        sqlx::query_as::<_, (String,)>(
+            "SELECT role FROM bckt_workspace_members WHERE workspace_id = $1 AND member_email = $2",
+        )
+        .bind(workspace_id)
+        .bind(email_hash)
+        .fetch_one(&self.pool)
+        .await
+        .ok()
+        .and_then(|(role,)| role.parse().ok())
+    }
+
+    pub async fn get_workspace_members(&self, workspace_id: i32) -> Vec<WorkspaceMember> {
+        sqlx::query_as::<_, WorkspaceMember>(
+            "SELECT * FROM bckt_workspace_members WHERE workspace_id = $1 ORDER BY member_display",
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Add a member to a workspace, or update its role if already a member.
+    pub async fn set_workspace_member(
+        &self,
+        workspace_id: i32,
+        email_hash: &str,
+        email: &str,
+        role: WorkspaceRole,
+    ) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        if role != WorkspaceRole::Owner {
+            Self::ensure_other_owner(&mut tx, workspace_id, email_hash).await?;
+        }
+        sqlx::query(
+            "INSERT INTO bckt_workspace_members (workspace_id, member_email, member_display, role) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (workspace_id, member_email) DO UPDATE SET role = EXCLUDED.role",
+        )
+        .bind(workspace_id)
+        .bind(email_hash)
+        .bind(email)
+        .bind(role.as_str())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())
     }
 
-    pub async fn delete_shortlink(&self, id: &str, owner_email: &str) -> Result<(), String> {
-        let len = sqlx::query("DELETE FROM bckt_links WHERE owner_email = $1 AND link_hash = $2")
+    pub async fn remove_workspace_member(
+        &self,
+        workspace_id: i32,
+        email_hash: &str,
+    ) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        Self::ensure_other_owner(&mut tx, workspace_id, email_hash).await?;
+        let len = sqlx::query(
+            "DELETE FROM bckt_workspace_members WHERE workspace_id = $1 AND member_email = $2",
+        )
+        .bind(workspace_id)
+        .bind(email_hash)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            return Err("no such member exists in this workspace".to_string());
+        }
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+
+    /// Ensure that a workspace does not lose its last owner,
+    /// in case the given member would no longer be an owner.
+    ///
+    /// All owners are locked until the transaction ends, such that owners
+    /// demoting or removing each other at the same time cannot both succeed.
+    async fn ensure_other_owner(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        workspace_id: i32,
+        email_hash: &str,
+    ) -> Result<(), String> {
+        let owners = sqlx::query_scalar::<_, String>(
+            "SELECT member_email FROM bckt_workspace_members
+                WHERE workspace_id = $1 AND role = 'owner'
+                ORDER BY member_email
+                FOR UPDATE",
+        )
+        .bind(workspace_id)
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        if owners.iter().all(|owner| owner == email_hash) {
+            Err("a workspace requires at least one other owner".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the webhooks to notify of a click on the given shortlink,
+    /// which are the ones registered for the shortlink by someone allowed to edit it,
+    /// and the ones registered for all personal shortlinks of its owner.
+    pub async fn get_webhooks_for_shortlink(&self, shortlink: &Shortlink) -> Vec<Webhook> {
+        let key = (
+            shortlink.domain().map(str::to_owned),
+            shortlink.link_hash().to_owned(),
+        );
+        if let Some(webhooks) = self.webhook_cache.get(&key) {
+            return webhooks.unwrap_or_default();
+        }
+        match sqlx::query_as::<_, Webhook>(&format!(
+            "SELECT w.id, w.url, w.secret, wl.link_hash, wl.domain
+                FROM bckt_webhooks w
+                LEFT JOIN bckt_links wl ON wl.id = w.link_id
+                JOIN bckt_links l ON l.link_hash = $1 AND l.domain IS NOT DISTINCT FROM $2
+                WHERE (w.link_id = l.id AND {})
+                    OR (w.link_id IS NULL AND l.workspace_id IS NULL
+                        AND l.owner_email = w.owner_email)",
+            can_edit_link("w.owner_email")
+        ))
+        .bind(shortlink.link_hash())
+        .bind(shortlink.domain())
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(webhooks) => {
+                self.webhook_cache.insert(key, Some(webhooks.clone()));
+                webhooks
+            }
+            Err(err) => {
+                tracing::error!(
+                    "Failed to get webhooks for shortlink {}: {}",
+                    shortlink.link_hash(),
+                    err
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    pub async fn get_webhooks_for_owner(&self, owner_email: &str) -> Vec<Webhook> {
+        sqlx::query_as::<_, Webhook>(
+            "SELECT w.id, w.url, w.secret, l.link_hash, l.domain
+                FROM bckt_webhooks w LEFT JOIN bckt_links l ON l.id = w.link_id
+                WHERE w.owner_email = $1 ORDER BY w.id",
+        )
+        .bind(owner_email)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Add a webhook for all personal shortlinks of the owner,
+    /// or for a single shortlink in case one is given, which the owner has to be allowed to edit.
+    pub async fn add_webhook(
+        &self,
+        owner_email: &str,
+        url: &str,
+        secret: &str,
+        link: Option<(&str, Option<&str>)>,
+    ) -> Result<(), String> {
+        let for_link = format!(
+            "INSERT INTO bckt_webhooks (owner_email, link_id, url, secret)
+                SELECT $1, l.id, $2, $3 FROM bckt_links l
+                WHERE l.link_hash = $4 AND l.domain IS NOT DISTINCT FROM $5 AND {}",
+            can_edit_link("$1")
+        );
+        let len = match link {
+            Some((id, domain)) => sqlx::query(&for_link)
+                .bind(owner_email)
+                .bind(url)
+                .bind(secret)
+                .bind(id)
+                .bind(domain),
+            None => sqlx::query(
+                "INSERT INTO bckt_webhooks (owner_email, url, secret) VALUES ($1, $2, $3)",
+            )
             .bind(owner_email)
+            .bind(url)
+            .bind(secret),
+        }
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err("no shortlink exists that you are allowed to edit".to_owned())
+        } else {
+            // a webhook for all personal shortlinks of the owner applies to many of them
+            self.webhook_cache.invalidate_where(|_| true);
+            Ok(())
+        }
+    }
+
+    pub async fn delete_webhook(&self, id: i32, owner_email: &str) -> Result<(), String> {
+        let len = sqlx::query("DELETE FROM bckt_webhooks WHERE id = $1 AND owner_email = $2")
             .bind(id)
+            .bind(owner_email)
             .execute(&self.pool)
             .await
             .map_err(|e| e.to_string())
             .map(|result| result.rows_affected())?;
         if len == 0 {
-            Err(format!("no shortlink '{id}' exists for current owner"))
+            Err(format!("no webhook '{id}' exists that you own"))
         } else {
+            self.webhook_cache.invalidate_where(|_| true);
             Ok(())
         }
     }
+
+    /// Get the most recent delivery attempts of the webhooks of the owner.
+    pub async fn get_webhook_deliveries(
+        &self,
+        owner_email: &str,
+        limit: i64,
+    ) -> Vec<WebhookDelivery> {
+        sqlx::query_as::<_, WebhookDelivery>(
+            "SELECT d.webhook_id, d.event_id, d.attempt, d.status, d.error, d.delivered_at
+                FROM bckt_webhook_deliveries d JOIN bckt_webhooks w ON w.id = d.webhook_id
+                WHERE w.owner_email = $1
+                ORDER BY d.delivered_at DESC LIMIT $2",
+        )
+        .bind(owner_email)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Forget delivery attempts of webhooks older than a week, as only recent ones are shown.
+    pub async fn purge_webhook_deliveries(&self) -> Result<u64, String> {
+        sqlx::query(
+            "DELETE FROM bckt_webhook_deliveries WHERE delivered_at <= NOW() - INTERVAL '7 days'",
+        )
+        .execute(&self.pool)
+        .await
+        .map(|result| result.rows_affected())
+        .map_err(|e| e.to_string())
+    }
+
+    /// The address to which the weekly digest is sent, if the user opted in to it.
+    pub async fn get_digest_email(&self, owner_email: &str) -> Option<String> {
+        sqlx::query_scalar::<_, Option<String>>(
+            "SELECT digest_email FROM bckt_preferences WHERE owner_email = $1",
+        )
+        .bind(owner_email)
+        .fetch_optional(&self.pool)
+        .await
+        .ok()
+        .flatten()
+        .flatten()
+    }
+
+    /// Opt in to the weekly digest with the given address, or opt out if none is given.
+    pub async fn set_digest_email(
+        &self,
+        owner_email: &str,
+        digest_email: Option<&str>,
+    ) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO bckt_preferences (owner_email, digest_email) VALUES ($1, $2)
+                ON CONFLICT (owner_email) DO UPDATE SET digest_email = $2",
+        )
+        .bind(owner_email)
+        .bind(digest_email)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|_| ())
+    }
+
+    /// Get the users (hash and address) which opted in to the weekly digest
+    /// and did not receive one within the last six days.
+    pub async fn get_digest_subscribers_due(&self) -> Vec<(String, String)> {
+        sqlx::query_as::<_, (String, String)>(
+            "SELECT owner_email, digest_email FROM bckt_preferences
+                WHERE digest_email IS NOT NULL
+                    AND (digest_sent_at IS NULL OR digest_sent_at < NOW() - INTERVAL '6 days')",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    pub async fn set_digest_sent(&self, owner_email: &str) -> Result<(), String> {
+        sqlx::query("UPDATE bckt_preferences SET digest_sent_at = NOW() WHERE owner_email = $1")
+            .bind(owner_email)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())
+            .map(|_| ())
+    }
+
+    /// Get the clicks on the personal shortlinks of the owner within the last `days`,
+    /// as `(link_hash, domain, clicks)`.
+    pub async fn get_recent_clicks_for_owner(
+        &self,
+        owner_email: &str,
+        days: i32,
+    ) -> Vec<(String, Option<String>, i64)> {
+        sqlx::query_as::<_, (String, Option<String>, i64)>(
+            "SELECT l.link_hash, l.domain, SUM(c.clicks)::BIGINT
+                FROM bckt_link_clicks_daily c JOIN bckt_links l ON l.id = c.link_id
+                WHERE l.owner_email = $1 AND l.workspace_id IS NULL
+                    AND c.day > CURRENT_DATE - $2
+                GROUP BY l.link_hash, l.domain",
+        )
+        .bind(owner_email)
+        .bind(days)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Get the referring hosts seen for the first time within the last `days`
+    /// on the personal shortlinks of the owner, as `(link_hash, domain, referrer_host)`.
+    pub async fn get_new_referrers_for_owner(
+        &self,
+        owner_email: &str,
+        days: i32,
+    ) -> Vec<(String, Option<String>, String)> {
+        sqlx::query_as::<_, (String, Option<String>, String)>(
+            "SELECT l.link_hash, l.domain, r.referrer_host
+                FROM bckt_link_referrers r JOIN bckt_links l ON l.id = r.link_id
+                WHERE l.owner_email = $1 AND l.workspace_id IS NULL
+                    AND r.first_seen_at > NOW() - make_interval(days => $2)
+                ORDER BY r.clicks DESC",
+        )
+        .bind(owner_email)
+        .bind(days)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    pub async fn add_secret(&self, secret: &Secret) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO bckt_secrets (id, owner_email, ciphertext, burn_after_reading, created_at, expires_at,
+                    passphrase_salt, passphrase_verifier, attempts_left, file_key, file_size, receipt_email,
+                    max_views, views_left, recipient_email_hash)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)",
+        )
+        .bind(secret.id())
+        .bind(secret.owner_email())
+        .bind(secret.ciphertext())
+        .bind(secret.burn_after_reading())
+        .bind(secret.created_at())
+        .bind(secret.expires_at())
+        .bind(secret.passphrase_salt())
+        .bind(secret.passphrase_verifier())
+        .bind(secret.attempts_left())
+        .bind(secret.file_key())
+        .bind(secret.file_size())
+        .bind(secret.receipt_email())
+        .bind(secret.max_views())
+        .bind(secret.views_left())
+        .bind(secret.recipient_email_hash())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|_| ())
+    }
+
+    pub async fn get_secret(&self, id: &str) -> Option<Secret> {
+        sqlx::query_as::<_, Secret>("SELECT * FROM bckt_secrets WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Claim sending a login link to the recipient of a secret, which is refused while
+    /// one was sent within the last five minutes, such that the recipient cannot be spammed.
+    pub async fn claim_recipient_login(&self, id: &str) -> Result<bool, String> {
+        sqlx::query(
+            "UPDATE bckt_secrets SET recipient_login_sent_at = NOW()
+                WHERE id = $1 AND (recipient_login_sent_at IS NULL
+                    OR recipient_login_sent_at <= NOW() - INTERVAL '5 minutes')",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    /// Get the most recent secrets created by the owner, including those which are gone.
+    pub async fn get_secrets_for_owner(&self, owner_email: &str) -> Vec<Secret> {
+        sqlx::query_as::<_, Secret>(
+            "SELECT * FROM bckt_secrets WHERE owner_email = $1 ORDER BY created_at DESC LIMIT 100",
+        )
+        .bind(owner_email)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Revoke a secret of the owner, removing its ciphertext right away,
+    /// and returning the key of its file, if any, which can no longer be downloaded.
+    pub async fn revoke_secret(
+        &self,
+        id: &str,
+        owner_email: &str,
+    ) -> Result<Option<String>, String> {
+        sqlx::query_scalar::<_, Option<String>>(
+            "UPDATE bckt_secrets
+                SET ciphertext = NULL, revoked_at = NOW(), file_ticket = NULL
+                WHERE id = $1 AND owner_email = $2 AND ciphertext IS NOT NULL
+                RETURNING file_key",
+        )
+        .bind(id)
+        .bind(owner_email)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "no such secret can be revoked by the current owner".to_string())
+    }
+
+    /// Extend the expiry of a secret of the owner which can still be read,
+    /// though never beyond the longest time a secret can live.
+    pub async fn extend_secret(
+        &self,
+        id: &str,
+        owner_email: &str,
+        hours: i32,
+    ) -> Result<(), String> {
+        let len = sqlx::query(
+            "UPDATE bckt_secrets
+                SET expires_at = LEAST(expires_at + $3 * INTERVAL '1 hour', created_at + $4 * INTERVAL '1 hour')
+                WHERE id = $1 AND owner_email = $2 AND ciphertext IS NOT NULL AND expires_at > NOW()",
+        )
+        .bind(id)
+        .bind(owner_email)
+        .bind(hours)
+        .bind(MAX_TTL_HOURS as i32)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|result| result.rows_affected())?;
+        if len == 0 {
+            Err("no such secret can be extended by the current owner".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the ciphertext of a secret in order to reveal it, counting down its views within
+    /// the same transaction if the secret burns after reading, and removing it after the last
+    /// one, such that it can never be revealed more often than its creator allowed.
+    ///
+    /// Secrets protected by a passphrase are only revealed given the right verifier,
+    /// and are destroyed once they run out of attempts.
+    ///
+    /// The viewer is only remembered for secrets of which the creator asked for a read receipt.
+    /// Secrets bound to a recipient are only revealed to visitors logged in as that recipient,
+    /// identified by the hash of their email address.
+    pub async fn reveal_secret(
+        &self,
+        id: &str,
+        verifier: Option<&str>,
+        viewer: &str,
+        visitor_email_hash: Option<&str>,
+    ) -> Result<SecretReveal, String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        // the row lock makes concurrent reveals wait, after which the ciphertext might be gone
+        let secret = match sqlx::query_as::<_, Secret>(
+            "SELECT * FROM bckt_secrets
+                WHERE id = $1 AND ciphertext IS NOT NULL AND expires_at > NOW()
+                FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        {
+            Some(secret) => secret,
+            None => return Ok(SecretReveal::Gone),
+        };
+
+        if !secret.is_readable_by(visitor_email_hash) {
+            return Ok(SecretReveal::NotRecipient);
+        }
+
+        if let Some(verifier_hash) = secret.passphrase_verifier() {
+            let verified = verifier
+                .map(|verifier| Secret::verify_passphrase(verifier_hash, verifier))
+                .unwrap_or_default();
+            if !verified {
+                let attempts_left = secret.attempts_left().unwrap_or_default() - 1;
+                sqlx::query(
+                    "UPDATE bckt_secrets
+                        SET attempts_left = $2,
+                            ciphertext = CASE WHEN $2 > 0 THEN ciphertext ELSE NULL END
+                        WHERE id = $1",
+                )
+                .bind(id)
+                .bind(attempts_left)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+                tx.commit().await.map_err(|e| e.to_string())?;
+                return Ok(if attempts_left > 0 {
+                    SecretReveal::WrongPassphrase { attempts_left }
+                } else {
+                    SecretReveal::Gone
+                });
+            }
+        }
+
+        // the file of a secret can only be downloaded for a short while after revealing it
+        let file_ticket = secret.file_key().map(|_| nanoid::nanoid!(24));
+        sqlx::query(
+            "UPDATE bckt_secrets
+                SET ciphertext = CASE WHEN burn_after_reading AND COALESCE(views_left, 1) <= 1
+                        THEN NULL ELSE ciphertext END,
+                    views_left = views_left - 1,
+                    viewed_at = NOW(),
+                    viewed_by = CASE WHEN receipt_email IS NULL THEN NULL ELSE $3 END,
+                    file_ticket = $2,
+                    file_ticket_expires_at = NOW() + INTERVAL '10 minutes'
+                WHERE id = $1",
+        )
+        .bind(id)
+        .bind(&file_ticket)
+        .bind(viewer)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+        self.secret_counters.read.fetch_add(1, Ordering::Relaxed);
+        Ok(SecretReveal::Revealed {
+            ciphertext: secret.ciphertext().unwrap_or_default().to_owned(),
+            file_ticket,
+            receipt_email: secret.receipt_email().map(str::to_owned),
+        })
+    }
+
+    /// Use a ticket to download the file of a secret, returning the key of its blob.
+    ///
+    /// The ticket of a secret which burned after reading can only be used once.
+    pub async fn use_file_ticket(&self, id: &str, ticket: &str) -> Option<String> {
+        sqlx::query_scalar::<_, String>(
+            "UPDATE bckt_secrets
+                SET file_ticket = CASE WHEN ciphertext IS NULL THEN NULL ELSE file_ticket END
+                WHERE id = $1 AND file_ticket = $2 AND file_ticket_expires_at > NOW()
+                    AND expires_at > NOW() AND file_key IS NOT NULL
+                RETURNING file_key",
+        )
+        .bind(id)
+        .bind(ticket)
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap_or_else(|err| {
+            tracing::error!("Failed to use file ticket of secret {}: {}", id, err);
+            None
+        })
+    }
+
+    /// Keep track of an uploaded blob, before it is stored,
+    /// such that it is purged if it never ends up being part of a secret.
+    pub async fn add_blob(&self, key: &str, owner_email: &str, size: i64) -> Result<(), String> {
+        sqlx::query("INSERT INTO bckt_blobs (key, owner_email, size) VALUES ($1, $2, $3)")
+            .bind(key)
+            .bind(owner_email)
+            .bind(size)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())
+            .map(|_| ())
+    }
+
+    /// Get the size of a blob uploaded by the owner, which is not yet part of a secret.
+    pub async fn get_unattached_blob_size(&self, key: &str, owner_email: &str) -> Option<i64> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT b.size FROM bckt_blobs b
+                WHERE b.key = $1 AND b.owner_email = $2
+                    AND NOT EXISTS (SELECT 1 FROM bckt_secrets s WHERE s.file_key = b.key)",
+        )
+        .bind(key)
+        .bind(owner_email)
+        .fetch_optional(&self.pool)
+        .await
+        .ok()
+        .flatten()
+    }
+
+    /// Get the keys of blobs which are no longer needed: those of secrets which can no longer
+    /// be downloaded, and those which were uploaded over an hour ago but never became a secret.
+    pub async fn get_unused_blobs(&self) -> Vec<String> {
+        sqlx::query_scalar::<_, String>(
+            "SELECT b.key FROM bckt_blobs b LEFT JOIN bckt_secrets s ON s.file_key = b.key
+                WHERE CASE WHEN s.id IS NULL THEN b.created_at < NOW() - INTERVAL '1 hour'
+                    ELSE s.expires_at <= NOW() OR (
+                        s.ciphertext IS NULL
+                        AND (s.file_ticket IS NULL OR s.file_ticket_expires_at <= NOW())
+                    )
+                END
+                LIMIT 1000",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    pub async fn delete_blob(&self, key: &str) -> Result<(), String> {
+        sqlx::query("DELETE FROM bckt_blobs WHERE key = $1")
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())
+            .map(|_| ())
+    }
+
+    pub async fn add_secret_request(&self, request: &SecretRequest) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO bckt_secret_requests (id, owner_email, label, public_key, created_at, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(request.id())
+        .bind(request.owner_email())
+        .bind(request.label())
+        .bind(request.public_key())
+        .bind(request.created_at())
+        .bind(request.expires_at())
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    }
+
+    pub async fn get_secret_request(&self, id: &str) -> Option<SecretRequest> {
+        sqlx::query_as::<_, SecretRequest>("SELECT * FROM bckt_secret_requests WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Get the most recent requests for secrets created by the owner.
+    pub async fn get_secret_requests_for_owner(&self, owner_email: &str) -> Vec<SecretRequest> {
+        sqlx::query_as::<_, SecretRequest>(
+            "SELECT * FROM bckt_secret_requests WHERE owner_email = $1 ORDER BY created_at DESC LIMIT 100",
+        )
+        .bind(owner_email)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Answer a request for a secret, returning whether it was still open.
+    ///
+    /// A request can only be answered once, such that nobody can replace the answer.
+    pub async fn answer_secret_request(
+        &self,
+        id: &str,
+        ciphertext: &str,
+        ephemeral_key: &str,
+    ) -> Result<bool, String> {
+        sqlx::query(
+            "UPDATE bckt_secret_requests
+                SET ciphertext = $2, ephemeral_key = $3, answered_at = NOW()
+                WHERE id = $1 AND answered_at IS NULL AND expires_at > NOW()",
+        )
+        .bind(id)
+        .bind(ciphertext)
+        .bind(ephemeral_key)
+        .execute(&self.pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .map_err(|e| e.to_string())
+    }
+
+    /// Remove the ciphertext of all expired secrets, returning how many were purged.
+    ///
+    /// The metadata of expired secrets is kept around for another month,
+    /// such that their creators can still see what happened to them.
+    pub async fn purge_expired_secrets(&self) -> Result<u64, String> {
+        let purged = sqlx::query(
+            "UPDATE bckt_secrets SET ciphertext = NULL
+                WHERE ciphertext IS NOT NULL AND expires_at <= NOW()",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+        self.secret_counters
+            .purged
+            .fetch_add(purged, Ordering::Relaxed);
+
+        sqlx::query("DELETE FROM bckt_secrets WHERE expires_at <= NOW() - INTERVAL '30 days'")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // answers to requests are secrets as well
+        let purged_answers = sqlx::query(
+            "UPDATE bckt_secret_requests SET ciphertext = NULL
+                WHERE ciphertext IS NOT NULL AND expires_at <= NOW()",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+        self.secret_counters
+            .purged
+            .fetch_add(purged_answers, Ordering::Relaxed);
+
+        sqlx::query(
+            "DELETE FROM bckt_secret_requests WHERE expires_at <= NOW() - INTERVAL '30 days'",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        Ok(purged + purged_answers)
+    }
+}
+
+#[async_trait]
+impl WebhookLog for Storage {
+    async fn log_webhook_delivery(
+        &self,
+        webhook_id: i32,
+        event_id: &str,
+        attempt: u32,
+        status: Option<u16>,
+        error: Option<&str>,
+    ) {
+        if let Err(err) = sqlx::query(
+            "INSERT INTO bckt_webhook_deliveries (webhook_id, event_id, attempt, status, error)
+                VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(webhook_id)
+        .bind(event_id)
+        .bind(attempt as i32)
+        .bind(status.map(i32::from))
+        .bind(error)
+        .execute(&self.pool)
+        .await
+        {
+            tracing::error!(
+                "Failed to log delivery of event {} to webhook {}: {}",
+                event_id,
+                webhook_id,
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Connect to a local Postgres, e.g. started with
+    /// `docker run -p 5432:5432 -e POSTGRES_HOST_AUTH_METHOD=trust postgres`,
+    /// by setting `BCKT_TEST_DATABASE_URL=postgres://postgres@localhost/postgres`.
+    async fn storage() -> Storage {
+        let url = std::env::var("BCKT_TEST_DATABASE_URL")
+            .unwrap_or_else(|_| "postgres://postgres@localhost/postgres".to_owned());
+        let pool = PgPool::connect(&url).await.unwrap();
+        sqlx::Executor::execute(&pool, include_str!("../../scripts/storage.sql"))
+            .await
+            .unwrap();
+        Storage::new(pool)
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Postgres"]
+    async fn test_reveal_secret_race() {
+        let storage = storage().await;
+
+        let secret = Secret::new("a".repeat(40), "owner".to_owned(), true, 1);
+        storage.add_secret(&secret).await.unwrap();
+
+        let (first, second) = tokio::join!(
+            storage.reveal_secret(secret.id(), None, "first", None),
+            storage.reveal_secret(secret.id(), None, "second", None),
+        );
+        let mut reveals = [first.unwrap(), second.unwrap()];
+        reveals.sort_by_key(|reveal| matches!(reveal, SecretReveal::Gone));
+        assert!(matches!(reveals[0], SecretReveal::Revealed { .. }));
+        assert_eq!(reveals[1], SecretReveal::Gone);
+        assert!(storage.get_secret(secret.id()).await.unwrap().is_burned());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Postgres"]
+    async fn test_manage_secret() {
+        let storage = storage().await;
+        let secret = Secret::new("a".repeat(40), "owner".to_owned(), false, 1);
+        storage.add_secret(&secret).await.unwrap();
+
+        // only the owner can manage a secret
+        assert!(storage.revoke_secret(secret.id(), "other").await.is_err());
+        assert!(storage
+            .extend_secret(secret.id(), "other", 24)
+            .await
+            .is_err());
+
+        // the expiry is capped at the longest time a secret can live
+        storage
+            .extend_secret(secret.id(), "owner", MAX_TTL_HOURS as i32)
+            .await
+            .unwrap();
+        let extended = storage.get_secret(secret.id()).await.unwrap();
+        assert_eq!(
+            extended.expires_at(),
+            extended.created_at() + chrono::Duration::hours(MAX_TTL_HOURS)
+        );
+        assert!(!extended.can_extend());
+
+        // revoked secrets can no longer be extended
+        assert_eq!(storage.revoke_secret(secret.id(), "owner").await, Ok(None));
+        assert!(storage
+            .extend_secret(secret.id(), "owner", 24)
+            .await
+            .is_err());
+        assert!(storage.revoke_secret(secret.id(), "owner").await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Postgres"]
+    async fn test_workspace_owner_race() {
+        let storage = storage().await;
+        let id = storage
+            .add_workspace(&nanoid::nanoid!(8), "first", "first@example.com")
+            .await
+            .unwrap();
+        storage
+            .set_workspace_member(id, "second", "second@example.com", WorkspaceRole::Owner)
+            .await
+            .unwrap();
+
+        // the owners demote and remove each other at the same time
+        let (demoted, removed) = tokio::join!(
+            storage.set_workspace_member(id, "second", "second@example.com", WorkspaceRole::Editor),
+            storage.remove_workspace_member(id, "first"),
+        );
+        assert!(demoted.is_ok() != removed.is_ok());
+        let owners = storage
+            .get_workspace_members(id)
+            .await
+            .into_iter()
+            .filter(|member| member.role() == WorkspaceRole::Owner)
+            .count();
+        assert_eq!(owners, 1);
+        storage.delete_workspace(id).await.unwrap();
+    }
 }
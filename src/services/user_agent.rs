@@ -0,0 +1,292 @@
+use axum::http::{
+    header::{ACCEPT, ACCEPT_LANGUAGE, USER_AGENT},
+    HeaderMap, Method,
+};
+
+/// The kind of visitor following a shortlink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visitor {
+    Human,
+    /// Crawlers, uptime monitors, security scanners and other automated clients.
+    Bot,
+    /// Chat apps, social networks and browsers fetching the link to render a preview.
+    Preview,
+}
+
+/// User-Agent fragments of the bots used by chat apps and social networks
+/// to unfurl links into a preview card.
+const UNFURL_BOTS: &[&str] = &[
+    "slackbot",
+    "slack-imgproxy",
+    "twitterbot",
+    "facebookexternalhit",
+    "facebot",
+    "linkedinbot",
+    "discordbot",
+    "telegrambot",
+    "whatsapp",
+    "skypeuripreview",
+    "microsoftpreview",
+    "mattermost-bot",
+    "mastodon",
+    "redditbot",
+    "pinterestbot",
+    "vkshare",
+    "iframely",
+    "embedly",
+    "applebot",
+];
+
+/// User-Agent fragments of crawlers, http libraries, monitors and scanners.
+const BOTS: &[&str] = &[
+    "bot",
+    "crawler",
+    "spider",
+    "slurp",
+    "curl",
+    "wget",
+    "httpie",
+    "python-requests",
+    "python-urllib",
+    "aiohttp",
+    "go-http-client",
+    "java/",
+    "okhttp",
+    "libwww-perl",
+    "httpclient",
+    "axios",
+    "node-fetch",
+    "headlesschrome",
+    "phantomjs",
+    "uptime",
+    "pingdom",
+    "statuscake",
+    "monitor",
+    "scanner",
+    "nmap",
+    "nikto",
+    "zgrab",
+    "masscan",
+    "censys",
+    "nessus",
+    "sqlmap",
+    "urlscan",
+    "virustotal",
+    "safebrowsing",
+    "proofpoint",
+    "mimecast",
+    "barracuda",
+];
+
+/// Classify a request based on its User-Agent and how it behaves,
+/// as not every automated client is honest about what it is.
+pub fn classify(method: &Method, headers: &HeaderMap) -> Visitor {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+    let user_agent = header(USER_AGENT.as_str());
+
+    // browsers announce speculative fetches, which are not followed by a person (yet)
+    let is_prefetch = ["purpose", "sec-purpose", "x-purpose", "x-moz"]
+        .iter()
+        .any(|name| {
+            let value = header(name);
+            value.contains("prefetch") || value.contains("preview")
+        });
+    if is_unfurl_bot(&user_agent) || is_prefetch {
+        return Visitor::Preview;
+    }
+
+    if method == Method::HEAD
+        || user_agent.is_empty()
+        || BOTS.iter().any(|bot| user_agent.contains(bot))
+    {
+        return Visitor::Bot;
+    }
+
+    // browsers always send what they accept, and a language unless they are scripted
+    let accept = header(ACCEPT.as_str());
+    if accept.is_empty()
+        || (!user_agent.starts_with("mozilla/") && header(ACCEPT_LANGUAGE.as_str()).is_empty())
+    {
+        return Visitor::Bot;
+    }
+
+    Visitor::Human
+}
+
+/// Whether or not the User-Agent belongs to a known link unfurling bot.
+pub fn is_unfurl_bot(user_agent: &str) -> bool {
+    let user_agent = user_agent.to_lowercase();
+    UNFURL_BOTS.iter().any(|bot| user_agent.contains(bot))
+}
+
+/// Describe the client of a request coarsely, by its browser and operating system only,
+/// such that it says something about who made the request without identifying them.
+pub fn describe(headers: &HeaderMap) -> String {
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    // order matters, as browsers include the names of those they are based on
+    let browser = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+    ]
+    .iter()
+    .find(|(fragment, _)| user_agent.contains(fragment))
+    .map(|(_, name)| *name);
+    let os = [
+        ("Windows", "Windows"),
+        ("Android", "Android"),
+        ("iPhone", "iOS"),
+        ("iPad", "iPadOS"),
+        ("Mac OS X", "macOS"),
+        ("CrOS", "ChromeOS"),
+        ("Linux", "Linux"),
+    ]
+    .iter()
+    .find(|(fragment, _)| user_agent.contains(fragment))
+    .map(|(_, name)| *name);
+    match (browser, os) {
+        (Some(browser), Some(os)) => format!("{browser} on {os}"),
+        (Some(browser), None) => browser.to_owned(),
+        (None, Some(os)) => format!("an unknown browser on {os}"),
+        (None, None) => "an unknown client".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let describe_ua = |user_agent: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(USER_AGENT, user_agent.parse().unwrap());
+            describe(&headers)
+        };
+        assert_eq!(
+            describe_ua("Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/119.0"),
+            "Firefox on Linux"
+        );
+        assert_eq!(
+            describe_ua("Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Mobile Safari/537.36"),
+            "Chrome on Android"
+        );
+        assert_eq!(
+            describe_ua("Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1"),
+            "Safari on iOS"
+        );
+        assert_eq!(describe(&HeaderMap::new()), "an unknown client");
+    }
+
+    #[test]
+    fn test_is_unfurl_bot() {
+        assert!(is_unfurl_bot(
+            "Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)"
+        ));
+        assert!(is_unfurl_bot("Twitterbot/1.0"));
+        assert!(is_unfurl_bot(
+            "facebookexternalhit/1.1 (+http://www.facebook.com/externalhit_uatext.php)"
+        ));
+        assert!(is_unfurl_bot(
+            "Mozilla/5.0 (compatible; Discordbot/2.0; +https://discordapp.com)"
+        ));
+        assert!(is_unfurl_bot("WhatsApp/2.23.20.0"));
+    }
+
+    #[test]
+    fn test_is_not_unfurl_bot() {
+        assert!(!is_unfurl_bot(
+            "Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/119.0"
+        ));
+        assert!(!is_unfurl_bot(
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Safari/605.1.15"
+        ));
+        assert!(!is_unfurl_bot(""));
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    const FIREFOX: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/119.0";
+
+    #[test]
+    fn test_classify_human() {
+        let headers = headers(&[
+            ("user-agent", FIREFOX),
+            ("accept", "text/html,*/*;q=0.8"),
+            ("accept-language", "en-US,en;q=0.5"),
+        ]);
+        assert_eq!(classify(&Method::GET, &headers), Visitor::Human);
+    }
+
+    #[test]
+    fn test_classify_bot() {
+        let browser = headers(&[("user-agent", FIREFOX), ("accept", "*/*")]);
+        assert_eq!(classify(&Method::GET, &browser), Visitor::Human);
+        assert_eq!(classify(&Method::HEAD, &browser), Visitor::Bot);
+        assert_eq!(
+            classify(&Method::GET, &headers(&[("user-agent", FIREFOX)])),
+            Visitor::Bot
+        );
+        assert_eq!(classify(&Method::GET, &headers(&[])), Visitor::Bot);
+        assert_eq!(
+            classify(
+                &Method::GET,
+                &headers(&[("user-agent", "curl/8.4.0"), ("accept", "*/*")])
+            ),
+            Visitor::Bot
+        );
+        assert_eq!(
+            classify(
+                &Method::GET,
+                &headers(&[
+                    ("user-agent", "Mozilla/5.0+(compatible; UptimeRobot/2.0)"),
+                    ("accept", "*/*")
+                ])
+            ),
+            Visitor::Bot
+        );
+        assert_eq!(
+            classify(
+                &Method::GET,
+                &headers(&[("user-agent", "some-script/1.0"), ("accept", "*/*")])
+            ),
+            Visitor::Bot
+        );
+    }
+
+    #[test]
+    fn test_classify_preview() {
+        assert_eq!(
+            classify(&Method::GET, &headers(&[("user-agent", "Twitterbot/1.0")])),
+            Visitor::Preview
+        );
+        assert_eq!(
+            classify(
+                &Method::GET,
+                &headers(&[
+                    ("user-agent", FIREFOX),
+                    ("accept", "*/*"),
+                    ("sec-purpose", "prefetch;prerender")
+                ])
+            ),
+            Visitor::Preview
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use orion::hazardous::mac::hmac::sha256::{HmacSha256, SecretKey};
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use tokio::sync::{mpsc, Semaphore};
+
+/// Header containing the hex encoded HMAC-SHA256 signature of the timestamp,
+/// a `.` and the body, computed with the secret of the webhook, prefixed with `sha256=`.
+pub const SIGNATURE_HEADER: &str = "x-bckt-signature";
+/// Header containing the unix timestamp at which the delivery was signed,
+/// such that receivers can refuse deliveries which are replayed later on.
+pub const TIMESTAMP_HEADER: &str = "x-bckt-timestamp";
+pub const EVENT_HEADER: &str = "x-bckt-event";
+pub const DELIVERY_HEADER: &str = "x-bckt-delivery";
+
+/// A click on a shortlink, as it is posted to webhooks.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClickEvent {
+    pub id: String,
+    pub event: &'static str,
+    pub short: String,
+    pub hash: String,
+    pub domain: Option<String>,
+    pub long: String,
+    pub clicked_at: String,
+}
+
+impl ClickEvent {
+    pub fn new(short: String, hash: String, domain: Option<String>, long: String) -> Self {
+        Self {
+            id: nanoid::nanoid!(16),
+            event: "click",
+            short,
+            hash,
+            domain,
+            long,
+            clicked_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// An event to be delivered to a single webhook.
+#[derive(Debug, Clone)]
+pub struct WebhookJob {
+    pub webhook_id: i32,
+    pub url: String,
+    pub secret: String,
+    pub event: ClickEvent,
+}
+
+/// Keeps track of the delivery attempts of webhooks.
+#[async_trait]
+pub trait WebhookLog: std::fmt::Debug + Send + Sync {
+    async fn log_webhook_delivery(
+        &self,
+        webhook_id: i32,
+        event_id: &str,
+        attempt: u32,
+        status: Option<u16>,
+        error: Option<&str>,
+    );
+}
+
+/// Sign a payload with the secret of the webhook.
+pub fn sign(secret: &str, payload: &[u8]) -> String {
+    let key = SecretKey::from_slice(secret.as_bytes()).expect("create webhook signing key");
+    let tag = HmacSha256::hmac(&key, payload).expect("sign webhook payload");
+    format!("sha256={}", hex::encode(tag.unprotected_as_bytes()))
+}
+
+/// Sign the body of a webhook delivery along with the time it is sent at.
+pub fn sign_delivery(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut payload = format!("{timestamp}.").into_bytes();
+    payload.extend_from_slice(body);
+    sign(secret, &payload)
+}
+
+/// Delivers webhook events in the background, such that clicks are not slowed down,
+/// retrying failed deliveries with an exponential backoff.
+///
+/// The urls of webhooks are only ever requested if they (still) point to a public address.
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcher {
+    sender: mpsc::Sender<WebhookJob>,
+}
+
+impl WebhookDispatcher {
+    pub fn spawn(log: Arc<dyn WebhookLog>, max_attempts: u32, backoff: Duration) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<WebhookJob>(1024);
+        let client = super::net::guarded_client(0)
+            .timeout(Duration::from_secs(5))
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent("bckt.xyz-webhook (+https://bckt.xyz/code)")
+            .build()
+            .expect("build webhook http client");
+        // limit the amount of concurrent deliveries
+        let permits = Arc::new(Semaphore::new(16));
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                let permit = match permits.clone().acquire_owned().await {
+                    Ok(permit) => permit,
+                    Err(_) => break,
+                };
+                let client = client.clone();
+                let log = log.clone();
+                tokio::spawn(async move {
+                    let public = reqwest::Url::parse(&job.url)
+                        .map(|url| super::net::is_public_url(&url))
+                        .unwrap_or_default();
+                    if !public {
+                        let error = "the url does not point to a public address";
+                        log.log_webhook_delivery(
+                            job.webhook_id,
+                            &job.event.id,
+                            1,
+                            None,
+                            Some(error),
+                        )
+                        .await;
+                    } else {
+                        deliver(&client, log.as_ref(), &job, max_attempts, backoff).await;
+                    }
+                    drop(permit);
+                });
+            }
+        });
+        Self { sender }
+    }
+
+    pub fn enqueue(&self, job: WebhookJob) {
+        if let Err(err) = self.sender.try_send(job) {
+            tracing::error!("Failed to enqueue webhook delivery: {}", err);
+        }
+    }
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    log: &dyn WebhookLog,
+    job: &WebhookJob,
+    max_attempts: u32,
+    backoff: Duration,
+) {
+    let body = match serde_json::to_vec(&job.event) {
+        Ok(body) => body,
+        Err(err) => {
+            tracing::error!("Failed to serialize webhook event: {}", err);
+            return;
+        }
+    };
+    for attempt in 1..=max_attempts {
+        // every attempt is signed anew, such that retries are not taken for replays
+        let timestamp = chrono::Utc::now().timestamp();
+        let result = client
+            .post(&job.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(
+                SIGNATURE_HEADER,
+                sign_delivery(&job.secret, timestamp, &body),
+            )
+            .header(EVENT_HEADER, job.event.event)
+            .header(DELIVERY_HEADER, &job.event.id)
+            .body(body.clone())
+            .send()
+            .await;
+        let retry = match result {
+            Ok(response) => {
+                let status = response.status();
+                log.log_webhook_delivery(
+                    job.webhook_id,
+                    &job.event.id,
+                    attempt,
+                    Some(status.as_u16()),
+                    None,
+                )
+                .await;
+                // client errors will not go away by trying again, unless we are asked to
+                !status.is_success()
+                    && (status.is_server_error()
+                        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                        || status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+            }
+            Err(err) => {
+                log.log_webhook_delivery(
+                    job.webhook_id,
+                    &job.event.id,
+                    attempt,
+                    None,
+                    Some(&err.to_string()),
+                )
+                .await;
+                true
+            }
+        };
+        if !retry {
+            return;
+        }
+        if attempt < max_attempts {
+            tokio::time::sleep(backoff * 2u32.pow(attempt - 1)).await;
+        }
+    }
+}
+
+/// Forget old delivery attempts every `interval`, as they pile up with every click.
+pub fn spawn_purge(storage: super::Storage, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match storage.purge_webhook_deliveries().await {
+                Ok(0) => (),
+                Ok(purged) => tracing::info!("Purged {} webhook deliveries", purged),
+                Err(err) => tracing::error!("Failed to purge webhook deliveries: {}", err),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[derive(Debug, Default)]
+    struct MemoryLog(Mutex<Vec<(u32, Option<u16>)>>);
+
+    #[async_trait]
+    impl WebhookLog for MemoryLog {
+        async fn log_webhook_delivery(
+            &self,
+            _webhook_id: i32,
+            _event_id: &str,
+            attempt: u32,
+            status: Option<u16>,
+            _error: Option<&str>,
+        ) {
+            self.0.lock().unwrap().push((attempt, status));
+        }
+    }
+
+    /// Read a full http request, returning its head and body.
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> (String, Vec<u8>) {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            data.extend_from_slice(&buf[..n]);
+            if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                let head = String::from_utf8_lossy(&data[..end]).to_lowercase();
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map(|value| value.trim().parse().unwrap())
+                    .unwrap_or_default();
+                while data.len() < end + 4 + length {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..n]);
+                }
+                return (head, data[end + 4..end + 4 + length].to_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            sign_delivery("secret", 1700000000, b"body"),
+            sign("secret", b"1700000000.body")
+        );
+        assert_ne!(
+            sign_delivery("secret", 1700000000, b"body"),
+            sign_delivery("secret", 1700000001, b"body")
+        );
+    }
+
+    fn job(url: String) -> WebhookJob {
+        WebhookJob {
+            webhook_id: 1,
+            url,
+            secret: "secret".to_owned(),
+            event: ClickEvent::new(
+                "https://bckt.xyz/abcd1234".to_owned(),
+                "abcd1234".to_owned(),
+                None,
+                "https://example.com/".to_owned(),
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deliver_retries() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let receiver = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for status in ["500 Internal Server Error", "200 OK"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                requests.push(read_request(&mut stream).await);
+                let response =
+                    format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        // the dispatcher refuses local addresses, which the test server has
+        let log = MemoryLog::default();
+        let job = job(format!("http://{addr}/hook"));
+        deliver(
+            &reqwest::Client::new(),
+            &log,
+            &job,
+            3,
+            Duration::from_millis(10),
+        )
+        .await;
+
+        let requests = receiver.await.unwrap();
+        for (head, body) in &requests {
+            let timestamp: i64 = head
+                .lines()
+                .find_map(|line| line.strip_prefix(&format!("{TIMESTAMP_HEADER}: ")))
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!((chrono::Utc::now().timestamp() - timestamp).abs() < 60);
+            let signature = format!(
+                "{}: {}",
+                SIGNATURE_HEADER,
+                sign_delivery("secret", timestamp, body)
+            );
+            assert!(head.contains(&signature));
+            let event: serde_json::Value = serde_json::from_slice(body).unwrap();
+            assert_eq!(event["event"], "click");
+            assert_eq!(event["hash"], "abcd1234");
+        }
+        assert_eq!(*log.0.lock().unwrap(), vec![(1, Some(500)), (2, Some(200))]);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_dispatcher_refuses_private_addresses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let log = Arc::new(MemoryLog::default());
+        let dispatcher = WebhookDispatcher::spawn(log.clone(), 3, Duration::from_millis(10));
+        dispatcher.enqueue(job(format!("http://{addr}/hook")));
+        dispatcher.enqueue(job(format!("http://localhost:{}/hook", addr.port())));
+
+        for _ in 0..100 {
+            if log.0.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(*log.0.lock().unwrap(), vec![(1, None), (1, None)]);
+        let accepted = tokio::time::timeout(Duration::from_millis(100), listener.accept()).await;
+        assert!(accepted.is_err());
+    }
+}
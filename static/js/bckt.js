@@ -1,6 +1,29 @@
+// key of the secret that was just encrypted, kept until its link is shown
+const SECRET_KEY_STORAGE = 'bckt-secret-key';
+// keys of secrets kept while their recipient logs in, for as long as the login link is valid
+const KEPT_KEY_TTL_MS = 60 * 60 * 1000;
+// private key of the secret request that was just created, kept until the request is stored
+const REQUEST_KEY_STORAGE = 'bckt-request-key';
+// iterations used to stretch the passphrase of a secret
+const PBKDF2_ITERATIONS = 600000;
+
+function base64UrlEncode(bytes) {
+    let binary = '';
+    bytes.forEach((b) => binary += String.fromCharCode(b));
+    return btoa(binary).replace(/\+/g, '-').replace(/\//g, '_').replace(/=+$/, '');
+}
+
+function base64UrlDecode(text) {
+    let base64 = text.replace(/-/g, '+').replace(/_/g, '/');
+    while (base64.length % 4) {
+        base64 += '=';
+    }
+    return Uint8Array.from(atob(base64), (c) => c.charCodeAt(0));
+}
+
 document.addEventListener('DOMContentLoaded', () => {
     document.body.addEventListener('htmx:beforeSwap', (evt) => {
-        if ([400, 401, 403, 404, 500].includes(evt.detail.xhr.status)) {
+        if ([400, 401, 403, 404, 429, 500].includes(evt.detail.xhr.status)) {
             evt.detail.shouldSwap = true;
             evt.detail.isError = false;
         }
@@ -17,7 +40,294 @@ document.addEventListener('DOMContentLoaded', () => {
         notify(message) {
             this._notify.success(message);
         }
+
+        error(message) {
+            this._notify.error(message);
+        }
+
+        // Derive the encryption key of a secret, and the verifier proving the passphrase to the server,
+        // from the key in the link and the passphrase, stretched using PBKDF2, such that both are needed.
+        async derivePassphraseKey(rawKey, passphrase, salt) {
+            const encoder = new TextEncoder();
+            const material = await crypto.subtle.importKey(
+                'raw', encoder.encode(passphrase), 'PBKDF2', false, ['deriveBits']);
+            const stretched = new Uint8Array(await crypto.subtle.deriveBits(
+                { name: 'PBKDF2', hash: 'SHA-256', salt, iterations: PBKDF2_ITERATIONS }, material, 256));
+            const combined = new Uint8Array(rawKey.length + stretched.length);
+            combined.set(rawKey);
+            combined.set(stretched, rawKey.length);
+            const hkdf = await crypto.subtle.importKey('raw', combined, 'HKDF', false, ['deriveBits', 'deriveKey']);
+            const key = await crypto.subtle.deriveKey(
+                { name: 'HKDF', hash: 'SHA-256', salt, info: encoder.encode('bckt-secret-key') },
+                hkdf, { name: 'AES-GCM', length: 256 }, false, ['encrypt', 'decrypt']);
+            const verifier = new Uint8Array(await crypto.subtle.deriveBits(
+                { name: 'HKDF', hash: 'SHA-256', salt, info: encoder.encode('bckt-secret-verifier') }, hkdf, 256));
+            return { key, verifier: base64UrlEncode(verifier) };
+        }
+
+        // Encrypt the secret of the form using a new AES-GCM key,
+        // such that only the ciphertext is submitted, and the key stays within the browser.
+        async encryptSecret(form) {
+            const plaintext = form.querySelector('[data-secret-plaintext]');
+            const fileInput = form.querySelector('[data-secret-file]');
+            const file = fileInput && fileInput.files[0];
+            if (!plaintext.value && !file) {
+                this.error('There is no secret to share.');
+                return;
+            }
+            if (file && file.size > Number(fileInput.dataset.maxSize)) {
+                this.error('The file is too large to share.');
+                return;
+            }
+            const rawKey = crypto.getRandomValues(new Uint8Array(32));
+            let key;
+            const passphrase = form.querySelector('[data-secret-passphrase]');
+            if (passphrase && passphrase.value) {
+                const salt = crypto.getRandomValues(new Uint8Array(16));
+                const derived = await this.derivePassphraseKey(rawKey, passphrase.value, salt);
+                key = derived.key;
+                form.querySelector('[name=passphrase_salt]').value = base64UrlEncode(salt);
+                form.querySelector('[name=passphrase_verifier]').value = derived.verifier;
+                passphrase.value = '';
+            } else {
+                key = await crypto.subtle.importKey('raw', rawKey, 'AES-GCM', false, ['encrypt']);
+            }
+            // the name and type of a file are part of the encrypted text, next to the message
+            let text = plaintext.value;
+            if (file) {
+                const response = await fetch('/secret/file', {
+                    method: 'POST',
+                    headers: { 'Content-Type': 'application/octet-stream' },
+                    body: await this.encrypt(key, new Uint8Array(await file.arrayBuffer())),
+                });
+                if (!response.ok) {
+                    this.error('The file could not be uploaded.');
+                    return;
+                }
+                form.querySelector('[name=file_key]').value = await response.text();
+                text = JSON.stringify({ text, name: file.name, type: file.type });
+                fileInput.value = '';
+            }
+            const data = await this.encrypt(key, new TextEncoder().encode(text));
+
+            form.querySelector('[name=ciphertext]').value = base64UrlEncode(data);
+            plaintext.value = '';
+            sessionStorage.setItem(SECRET_KEY_STORAGE, base64UrlEncode(rawKey));
+            form.submit();
+        }
+
+        // Keep the key of a secret while its recipient logs in, as the login link cannot carry it.
+        // The login link opens in another tab, so the key is kept in the local storage until it expires.
+        keepSecretKey(id) {
+            const fragment = window.location.hash.slice(1);
+            if (fragment) {
+                localStorage.setItem(`${SECRET_KEY_STORAGE}-${id}`,
+                    JSON.stringify({ key: fragment, expiresAt: Date.now() + KEPT_KEY_TTL_MS }));
+            }
+        }
+
+        // The kept key of a secret, if it did not expire yet.
+        keptSecretKey(id) {
+            try {
+                const kept = JSON.parse(localStorage.getItem(`${SECRET_KEY_STORAGE}-${id}`));
+                if (kept && kept.expiresAt > Date.now()) {
+                    return kept.key;
+                }
+            } catch (_) {
+                // kept without an expiry, which is no longer trusted
+            }
+            localStorage.removeItem(`${SECRET_KEY_STORAGE}-${id}`);
+            return null;
+        }
+
+        // Forget the kept keys which expired, even of secrets which are never visited again.
+        forgetExpiredSecretKeys() {
+            Object.keys(localStorage)
+                .filter((name) => name.startsWith(`${SECRET_KEY_STORAGE}-`))
+                .forEach((name) => this.keptSecretKey(name.slice(SECRET_KEY_STORAGE.length + 1)));
+        }
+
+        // The full link of a secret that was just created, including its key.
+        secretUrl(url) {
+            const key = sessionStorage.getItem(SECRET_KEY_STORAGE);
+            sessionStorage.removeItem(SECRET_KEY_STORAGE);
+            if (!key) {
+                this.error('The key of this secret is no longer known.');
+                return url;
+            }
+            return `${url}#${key}`;
+        }
+
+        // Derive the AES-GCM key of the answer to a secret request from an ECDH key agreement,
+        // salted with the public key of the ephemeral key pair of the sender.
+        async deriveRequestKey(privateKey, publicKey, ephemeralKey) {
+            const shared = await crypto.subtle.deriveBits({ name: 'ECDH', public: publicKey }, privateKey, 256);
+            const hkdf = await crypto.subtle.importKey('raw', shared, 'HKDF', false, ['deriveKey']);
+            return crypto.subtle.deriveKey(
+                { name: 'HKDF', hash: 'SHA-256', salt: ephemeralKey, info: new TextEncoder().encode('bckt-secret-request') },
+                hkdf, { name: 'AES-GCM', length: 256 }, false, ['encrypt', 'decrypt']);
+        }
+
+        // Request a secret using a new ECDH key pair, of which only the public key is submitted.
+        async createSecretRequest(form) {
+            const keyPair = await crypto.subtle.generateKey({ name: 'ECDH', namedCurve: 'P-256' }, true, ['deriveBits']);
+            const publicKey = new Uint8Array(await crypto.subtle.exportKey('raw', keyPair.publicKey));
+            form.querySelector('[name=public_key]').value = base64UrlEncode(publicKey);
+            sessionStorage.setItem(REQUEST_KEY_STORAGE,
+                JSON.stringify(await crypto.subtle.exportKey('jwk', keyPair.privateKey)));
+            form.submit();
+        }
+
+        // Keep the private key of the request that was just created, to decrypt its answer later on.
+        keepRequestKey(id) {
+            const key = sessionStorage.getItem(REQUEST_KEY_STORAGE);
+            sessionStorage.removeItem(REQUEST_KEY_STORAGE);
+            if (!key) {
+                this.error('The key of this request is no longer known.');
+                return;
+            }
+            localStorage.setItem(`${REQUEST_KEY_STORAGE}-${id}`, key);
+        }
+
+        // Encrypt the answer to a secret request to the public key of whoever requested it.
+        async answerSecretRequest(form) {
+            const plaintext = form.querySelector('[data-secret-plaintext]');
+            if (!plaintext.value) {
+                this.error('There is no secret to send.');
+                return;
+            }
+            const publicKey = await crypto.subtle.importKey(
+                'raw', base64UrlDecode(form.dataset.publicKey), { name: 'ECDH', namedCurve: 'P-256' }, false, []);
+            const ephemeral = await crypto.subtle.generateKey({ name: 'ECDH', namedCurve: 'P-256' }, true, ['deriveBits']);
+            const ephemeralKey = new Uint8Array(await crypto.subtle.exportKey('raw', ephemeral.publicKey));
+            const key = await this.deriveRequestKey(ephemeral.privateKey, publicKey, ephemeralKey);
+            const data = await this.encrypt(key, new TextEncoder().encode(plaintext.value));
+
+            form.querySelector('[name=ciphertext]').value = base64UrlEncode(data);
+            form.querySelector('[name=ephemeral_key]').value = base64UrlEncode(ephemeralKey);
+            plaintext.value = '';
+            form.submit();
+        }
+
+        // Decrypt the answer to a secret request into the given element,
+        // using the private key kept by this browser.
+        async openSecretRequest(id, ciphertext, ephemeralKey, el) {
+            const jwk = localStorage.getItem(`${REQUEST_KEY_STORAGE}-${id}`);
+            if (!jwk) {
+                this.error('The key of this request is only known by the browser which requested it.');
+                return false;
+            }
+            try {
+                const privateKey = await crypto.subtle.importKey(
+                    'jwk', JSON.parse(jwk), { name: 'ECDH', namedCurve: 'P-256' }, false, ['deriveBits']);
+                const ephemeralBytes = base64UrlDecode(ephemeralKey);
+                const publicKey = await crypto.subtle.importKey(
+                    'raw', ephemeralBytes, { name: 'ECDH', namedCurve: 'P-256' }, false, []);
+                const key = await this.deriveRequestKey(privateKey, publicKey, ephemeralBytes);
+                el.value = await this.decryptSecret(ciphertext, key);
+                return true;
+            } catch (_) {
+                this.error('The secret could not be decrypted.');
+                return false;
+            }
+        }
+
+        // Encrypt the data using AES-GCM, prefixing the result with its random nonce.
+        async encrypt(key, plaintext) {
+            const iv = crypto.getRandomValues(new Uint8Array(12));
+            const encrypted = new Uint8Array(await crypto.subtle.encrypt({ name: 'AES-GCM', iv }, key, plaintext));
+            const data = new Uint8Array(iv.length + encrypted.length);
+            data.set(iv);
+            data.set(encrypted, iv.length);
+            return data;
+        }
+
+        async decrypt(key, data) {
+            return crypto.subtle.decrypt({ name: 'AES-GCM', iv: data.slice(0, 12) }, key, data.slice(12));
+        }
+
+        async decryptSecret(ciphertext, key) {
+            return new TextDecoder().decode(await this.decrypt(key, base64UrlDecode(ciphertext)));
+        }
+
+        // Download and decrypt the file of a revealed secret, offering it to be saved using the link.
+        async revealFile(id, ticket, key, file, link) {
+            const response = await fetch(`/s/${id}/file?ticket=${encodeURIComponent(ticket)}`, { cache: 'no-store' });
+            if (!response.ok) {
+                this.error('The file of this secret could not be downloaded.');
+                return;
+            }
+            const decrypted = await this.decrypt(key, new Uint8Array(await response.arrayBuffer()));
+            link.href = URL.createObjectURL(new Blob([decrypted], { type: file.type || 'application/octet-stream' }));
+            link.download = file.name;
+            link.textContent = `📎 Save ${file.name}`;
+            link.style.display = '';
+        }
+
+        // Reveal a secret, fetching its ciphertext and decrypting it into the given element.
+        // A secret which burns after reading is gone once this is done.
+        async revealSecret(id, el, passphrase, fileLink) {
+            const keptKey = `${SECRET_KEY_STORAGE}-${id}`;
+            const fragment = window.location.hash.slice(1) || this.keptSecretKey(id);
+            if (!fragment) {
+                this.error('The link of this secret is missing its key.');
+                return false;
+            }
+            const rawKey = base64UrlDecode(fragment);
+            const body = new URLSearchParams();
+            let key;
+            if (passphrase) {
+                if (!passphrase.value) {
+                    this.error('This secret is protected by a passphrase.');
+                    return false;
+                }
+                const salt = base64UrlDecode(passphrase.dataset.passphraseSalt);
+                const derived = await this.derivePassphraseKey(rawKey, passphrase.value, salt);
+                key = derived.key;
+                body.set('verifier', derived.verifier);
+            } else {
+                key = await crypto.subtle.importKey('raw', rawKey, 'AES-GCM', false, ['decrypt']);
+            }
+            const response = await fetch(`/s/${id}/reveal`, { method: 'POST', body, cache: 'no-store' });
+            if (response.status === 401) {
+                this.error('This secret can only be read by its recipient, log in to read it.');
+                return false;
+            }
+            if (response.status === 403) {
+                passphrase.value = '';
+                this.error(`Wrong passphrase, ${await response.text()} attempts left.`);
+                return false;
+            }
+            if (response.status === 410 || response.status === 404) {
+                localStorage.removeItem(keptKey);
+                this.error(response.status === 410
+                    ? 'This secret was already viewed, or has expired.'
+                    : 'This secret does not exist (anymore).');
+                return false;
+            }
+            if (!response.ok) {
+                this.error('The secret could not be fetched.');
+                return false;
+            }
+            localStorage.removeItem(keptKey);
+            const ticket = response.headers.get('x-bckt-file-ticket');
+            try {
+                const text = await this.decryptSecret(await response.text(), key);
+                if (!ticket) {
+                    el.value = text;
+                    return true;
+                }
+                const file = JSON.parse(text);
+                el.value = file.text;
+                await this.revealFile(id, ticket, key, file, fileLink);
+                return true;
+            } catch (_) {
+                this.error('The secret could not be decrypted, is the link complete?');
+                return false;
+            }
+        }
     }
 
     window.bckt = new Bucket();
+    window.bckt.forgetExpiredSecretKeys();
 });